@@ -19,9 +19,10 @@
 //!   `fetch_weather_notify` method to get the weather data for that location.
 //! * The weather data is then used to display a notification to the user with the summary of the
 //!   weather.
-//! * The `OpenWeatherMap` API key is retrieved from the environment variables, and the request to
-//!   the `OpenWeatherMap` API is made using the reqwest library.
-//! * The response body is deserialized as a `OpenWeatherData` struct using the serde library.
+//! * Weather data comes from a `WeatherProvider` (see the `provider` module). The
+//!   `OpenWeatherMap` provider retrieves its API key from the environment variables, and the
+//!   request to the `OpenWeatherMap` API is made using the reqwest library.
+//! * Each provider maps its response body into the provider-neutral `Observation` struct.
 //!
 //! ## Dependencies
 //!
@@ -53,148 +54,542 @@
 //! The `fetch_weather_notify` function will return an error in the following cases:
 //!
 //! * `WEATHER_API_KEY` environment variable not found in .env file.
-//! * Failed to make the request to `OpenWeatherMap` API.
+//! * Failed to make the request to the weather provider.
 //! * Failed to deserialize the response body as JSON.
 
+mod alert;
+mod changes;
+mod forecast;
+mod http;
+mod icon;
+mod locate;
+mod location;
+mod provider;
 mod temperature;
+mod template;
+mod units;
 
-use std::{env, num::ParseFloatError, path::PathBuf};
+use std::{num::ParseFloatError, path::PathBuf, sync::Arc};
 
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use clap::Parser;
 use dotenv::dotenv;
 use lazy_static::lazy_static;
-use notify_rust::{Hint, Notification};
-use reqwest::{Client, Response};
+use notify_rust::{Hint, Notification, Timeout, Urgency};
 use rust_decimal::Decimal;
-
-use self::temperature::{TempUnit, Temperature};
-use crate::{cli::Cli, display_tempunit, models::OpenWeatherData};
+use serde::Serialize;
+
+pub use self::{
+    alert::AlertRule,
+    location::Strategy,
+    provider::{ProviderKind, Query},
+    temperature::{TempUnit, Temperature},
+    units::{DisplayUnits, DistanceUnit, PressureUnit, SpeedUnit},
+};
+use self::{
+    forecast::{summarize_days, upcoming_slots, DaySummary, HourSummary},
+    http::Http,
+    location::LocationResolver,
+    provider::WeatherProvider,
+};
+use crate::{
+    cli::{Cli, Command},
+    config::{Config, SavedLocation},
+    display_tempunit,
+    error::MausamError,
+    models::{Observation, Place, WeatherEntity},
+};
 
 lazy_static! {
     /// Define the URL as lazily loaded static
     pub static ref IP_API_URL: &'static str = "http://ip-api.com/json";
-    /// Define the reqwest::Client as lazily loaded static
-    pub static ref CLIENT: Client = Client::new();
 }
 
 // HACK: Can use RUST_PACKAGE name env?
 pub const APP_NAME: &str = "mausam";
 
+/// Category hint of the notifications, in the vendor namespace of the freedesktop spec.
+const NOTIFICATION_CATEGORY: &str = "x-mausam.weather";
+
+/// What a run of the app produced, ready to be printed.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Report {
+    /// The current weather.
+    Current(Observation),
+    /// One summary per forecast day.
+    Forecast(Vec<DaySummary>),
+    /// The upcoming forecast slots.
+    Hourly(Vec<HourSummary>),
+    /// The current weather at several named locations.
+    Locations(Vec<Observation>),
+    /// The places matching a place name.
+    Places(Vec<Place>),
+    /// The place saved as the named location `name` in the config file at `path`.
+    Saved { name: String, path: PathBuf, place: Place },
+    /// The effective configuration and the file it was loaded from.
+    Config { path: Option<PathBuf>, config: Config },
+}
+
 /// `run` function is the main function for the application.
 ///
-/// It loads the environment variables, retrieves the location of the user, and then gets the
-/// weather data for that location. The weather data is then returned as a
-/// `Result<OpenWeatherData>`.
+/// It loads the configuration, retrieves the location of the user, and then gets the
+/// weather data for that location. The weather data is then returned as a `Result<Report>`
+/// along with the configuration it was rendered with.
 ///
 /// # Errors
 ///
 /// This function will return an error in the following cases:
 ///
+/// * Failed to load the configuration.
+/// * No API key configured while the `openweathermap` provider is requested explicitly.
 /// * Failed to fetch IP API location.
 /// * Failed to parse CLI arguments.
 /// * Empty string passed for place.
 /// * Failed to fetch weather.
-pub async fn run() -> anyhow::Result<OpenWeatherData> {
+pub async fn run() -> anyhow::Result<(Report, Config)> {
     dotenv().ok();
 
-    let location = ipapi::get_ip_api_location().await?;
-    let city = location.city;
-    // println!("Your current city is: {city}.");
+    let args = Cli::parse();
+    let config = Config::load(&args)?;
+
+    if let Some(Command::Config) = args.command {
+        let report = Report::Config { path: Config::path(&args), config: config.clone() };
+        return Ok((report, config));
+    }
 
-    let mut args = Cli::parse();
-    let place = args.place.get_or_insert(city);
-    if place.is_empty() {
-        panic!("{:#?}", anyhow!("`{place}`").context("Empty string passed for place"));
+    let http = Http::new(&config.http)?;
+    let provider = provider::new_provider(&config, &http)?;
+
+    if let Some(Command::Locate { query, pick, country, save }) = &args.command {
+        let config_path = Config::path(&args);
+        let report = locate::locate(
+            provider.as_ref(),
+            query,
+            *pick,
+            country.as_deref(),
+            save.as_deref(),
+            config_path,
+        )
+        .await?;
+        return Ok((report, config));
     }
 
-    let data = (fetch_weather_notify(place).await)
-        .map_err(|err| err.context("Failed to fetch weather"))?;
+    let named = location::named_locations(&config, &args)?;
+    let forecast = args.hours.is_some() || matches!(args.command, Some(Command::Forecast { .. }));
+    if !named.is_empty() && !forecast {
+        let data = (fetch_locations_notify(Arc::clone(&provider), &named, &config).await)
+            .map_err(|err| err.context("Failed to fetch weather"))?;
+        return Ok((Report::Locations(data), config));
+    }
 
-    Ok(data)
+    let place = match named.as_slice() {
+        [] => resolve_place(&config, &args, &http).await?,
+        [(name, location)] => {
+            location.query().with_context(|| format!("Invalid location `{name}`"))?
+        }
+        _ => bail!("A forecast is for one location at a time, pass a single `--location`"),
+    };
+
+    let report = match args.command {
+        Some(Command::Forecast { notify, .. }) => {
+            let days = (fetch_forecast_notify(provider.as_ref(), &place, notify, &config).await)
+                .map_err(|err| err.context("Failed to fetch forecast"))?;
+
+            Report::Forecast(days)
+        }
+        _ => {
+            if let Some(hours) = args.hours {
+                let forecast = (provider.forecast(&place).await)
+                    .map_err(|err| err.context("Failed to fetch forecast"))?;
+                let now = chrono::Utc::now().timestamp();
+
+                Report::Hourly(upcoming_slots(&forecast, now, hours))
+            } else {
+                let data = (fetch_weather_notify(provider.as_ref(), &place, &config).await)
+                    .map_err(|err| err.context("Failed to fetch weather"))?;
+
+                Report::Current(data)
+            }
+        }
+    };
+
+    Ok((report, config))
 }
+
+/// The place or coordinates to query, from the first location strategy that answers.
+async fn resolve_place(config: &Config, args: &Cli, http: &Http) -> anyhow::Result<Query> {
+    let place = LocationResolver::new(config, args, http).resolve().await?;
+    // println!("Your current city is: {place}.");
+    if place == Query::Place(String::new()) {
+        return Err(anyhow!(MausamError::PlaceNotFound(place.to_string()))
+            .context("Empty string passed for place"));
+    }
+
+    Ok(place)
+}
+
+/// `fetch_forecast_notify` fetches the forecast for `query` and condenses it into one summary
+/// per day. With `notify` set, each day is also shown as its own notification.
+///
+/// # Errors
+///
+/// This function will return an error in the following cases:
+///
+/// * Failed to fetch the forecast from the provider.
+/// * Failed to show a notification.
+async fn fetch_forecast_notify(
+    provider: &dyn WeatherProvider,
+    query: &Query,
+    notify: bool,
+    config: &Config,
+) -> anyhow::Result<Vec<DaySummary>> {
+    let forecast = provider
+        .forecast(query)
+        .await
+        .with_context(|| format!("Failed to fetch forecast from `{}`", provider.name()))?;
+    let days = summarize_days(&forecast);
+
+    if notify {
+        let unit = config.units.temp_unit();
+        for (i, day) in days.iter().enumerate() {
+            let temp_min = Temperature::new(day.temp_min, TempUnit::Kelvin).to_unit(unit);
+            let temp_max = Temperature::new(day.temp_max, TempUnit::Kelvin).to_unit(unit);
+            let unit: &str = display_tempunit!(temp_min.unit);
+            let mut summary = format!("{} {}", forecast.name, day.date.format("%a %d %b"));
+            if let Some(stale) = &forecast.stale {
+                summary.push_str(&format!(" (as of {})", stale.as_of));
+            }
+
+            let notification = NotifyData::new()
+                .with_summary(summary.as_str())
+                .with_body(
+                    format!(
+                        "{}... {}{unit} / {}{unit}",
+                        capitalize(&day.weather.description),
+                        temp_min.value.floor(),
+                        temp_max.value.ceil()
+                    )
+                    .as_str(),
+                )
+                .with_icon(&weather_icon(&day.weather, config))
+                .with_urgency(weather_urgency(&day.weather));
+            show_notification(notification, &format!("forecast-{}-{i}", forecast.name), config)?;
+        }
+    }
+
+    Ok(days)
+}
+
 /// `fetch_weather_notify` function fetches the weather data for a specified location.
 ///
-/// It retrieves the `OpenWeatherMap` API key from the environment variables and then sends a
-/// request to the `OpenWeatherMap` API to get the weather data for the specified location. The data
-/// is then used to display a notification to the user with the summary of the weather.
+/// It asks the `provider` for the current observation at `query`. The observation is then used
+/// to display a notification to the user with the summary of the weather.
 ///
 /// # Errors
 ///
 /// This function will return an error in the following cases:
 ///
-/// * `WEATHER_API_KEY` environment variable not found in `.env` file.
-/// * Failed to make the request to `OpenWeatherMap` API.
-/// * Failed to deserialize the response body as JSON.
+/// * Failed to fetch the observation from the provider.
+/// * Failed to show the notification.
 // $ RUST_BACKTRACE=1 mausam
-// FIXME: embed default API key for when env is not found.
-async fn fetch_weather_notify(query: &str) -> anyhow::Result<OpenWeatherData> {
-    let api_var: &str = "WEATHER_API_KEY";
-    let weather_api_key: String = {
-        let dir: PathBuf = env::current_dir()?;
-        let ctx = anyhow!(
-            "`{api_var}` environment variable key not found in `{}/.env`",
-            dir.to_string_lossy()
-        );
-        match env::var(api_var).context(ctx) {
-            Ok(k) => k,
+async fn fetch_weather_notify(
+    provider: &dyn WeatherProvider,
+    query: &Query,
+    config: &Config,
+) -> anyhow::Result<Observation> {
+    let data = provider
+        .current(query)
+        .await
+        .with_context(|| format!("Failed to fetch weather from `{}`", provider.name()))?;
+
+    if let Some((summary, body, urgency)) = current_notification(&data, &data.name, config)? {
+        let notification = NotifyData::new()
+            .with_summary(summary.as_str())
+            .with_body(body.as_str())
+            .with_icon(&weather_icon(&data.weather, config))
+            .with_urgency(urgency);
+        if show_notification(notification, &format!("weather-{}", data.name), config)? {
+            changes::remember(&data, &data.name, config);
+        }
+    }
+
+    Ok(data)
+}
+
+/// `fetch_locations_notify` fetches the current weather at each named location concurrently.
+///
+/// Each location gets its own notification, or all share one with `notification.combine`.
+/// Locations that fail are logged and left out, labels replace the provider's place names.
+///
+/// # Errors
+///
+/// This function will return an error in the following cases:
+///
+/// * Failed to fetch the observation for every location.
+/// * Failed to show a notification.
+async fn fetch_locations_notify(
+    provider: Arc<dyn WeatherProvider>,
+    locations: &[(&str, &SavedLocation)],
+    config: &Config,
+) -> anyhow::Result<Vec<Observation>> {
+    let tasks: Vec<_> = locations
+        .iter()
+        .map(|(name, location)| {
+            let provider = Arc::clone(&provider);
+            let query = location.query().with_context(|| format!("Invalid location `{name}`"));
+            tokio::spawn(async move { provider.current(&query?).await })
+        })
+        .collect();
+
+    let mut observations = Vec::with_capacity(tasks.len());
+    let mut lines = Vec::with_capacity(tasks.len());
+    // The observations in the combined notification, remembered once it is shown.
+    let mut combined = Vec::with_capacity(tasks.len());
+    let mut critical = false;
+    for ((name, location), task) in locations.iter().zip(tasks) {
+        let mut data = match task.await? {
+            Ok(data) => data,
             Err(err) => {
-                log::error!("called `Result::unwrap()` on an `Err` value: {err:#?}");
-                eprintln!("{err}: `{}`", err.root_cause());
-                std::process::exit(1);
+                log::warn!(
+                    "Failed to fetch weather for `{name}` from `{}`: {err:#}",
+                    provider.name()
+                );
+                continue;
             }
+        };
+        if let Some(label) = &location.label {
+            data.name = label.clone();
         }
-    };
-    let url = format!(
-        "https://api.openweathermap.org/data/2.5/weather?q={query}&appid={weather_api_key}"
-    );
-    let data: OpenWeatherData = reqwest::get(url)
-        .await
-        .into_iter()
-        .find(|response| is_err_panic(response, query))
-        .unwrap()
-        .json()
-        .await
-        .map_err(|e| anyhow!(e).context("Failed to deserialize the response body as JSON."))?;
+
+        match current_notification(&data, name, config)? {
+            Some((summary, body, urgency)) if config.notification.combine => {
+                lines.push(format!("{summary}  {body}"));
+                combined.push((*name, data.clone()));
+                critical |= urgency == Urgency::Critical;
+            }
+            Some((summary, body, urgency)) => {
+                let notification = NotifyData::new()
+                    .with_summary(summary.as_str())
+                    .with_body(body.as_str())
+                    .with_icon(&weather_icon(&data.weather, config))
+                    .with_urgency(urgency);
+                if show_notification(notification, &format!("weather-{name}"), config)? {
+                    changes::remember(&data, name, config);
+                }
+            }
+            None => {}
+        }
+        observations.push(data);
+    }
+
+    if observations.is_empty() {
+        return Err(anyhow!("Failed to fetch weather for all {} locations", locations.len()));
+    }
+    if config.notification.combine && !lines.is_empty() {
+        let notification = NotifyData::new()
+            .with_summary(format!("Weather at {} locations", lines.len()).as_str())
+            .with_body(lines.join("\n").as_str())
+            .with_icon(&icon::resolve(icon::DEFAULT_ICON, config.notification.icon_dir.as_deref()))
+            .with_urgency(if critical { Urgency::Critical } else { Urgency::Normal });
+        if show_notification(notification, "weather-combined", config)? {
+            for (name, data) in &combined {
+                changes::remember(data, name, config);
+            }
+        }
+    }
+
+    Ok(observations)
+}
+
+/// Summary, body and urgency of the notification for the current weather at the location
+/// `key`, `None` when there is nothing worth notifying.
+///
+/// With `[[alerts]]` rules or change detection configured, only an observation matching a rule
+/// or changed since the last notification gets one. Matching rules make it critical, with a summary
+/// starting with them, e.g. `Frost: Paris -2.5°C`. Changes start the body, e.g.
+/// `Light rain, was clouds`.
+fn current_notification(
+    data: &Observation,
+    key: &str,
+    config: &Config,
+) -> anyhow::Result<Option<(String, String, Urgency)>> {
+    let (mut summary, mut body) = describe_current(data, config)?;
+    let mut urgency = weather_urgency(&data.weather);
+    let filtered = config.changes.enabled || !config.alerts.is_empty();
+    let mut notify = !filtered;
+
+    if config.changes.enabled {
+        match changes::since_last_notified(data, key, config) {
+            // Nothing to compare with yet.
+            None => notify = true,
+            Some(changes) if changes.is_empty() => {}
+            Some(changes) => {
+                body = format!("{}\n{body}", changes.join(", "));
+                notify = true;
+            }
+        }
+    }
+    let matching = alert::matching(&config.alerts, data, &DisplayUnits::from(config));
+    if !matching.is_empty() {
+        summary = format!("{}: {summary}", matching.join(", "));
+        urgency = Urgency::Critical;
+        notify = true;
+    }
+
+    if !notify {
+        log::info!("Nothing to notify about the weather in `{}`", data.name);
+        return Ok(None);
+    }
+    Ok(Some((summary, body, urgency)))
+}
+
+/// Notification summary and body for the current weather, from the `summary_template` and
+/// `body_template` of the notification config, by default e.g. `Paris 10.03°C` and
+/// `Overcast clouds... 9°C / 11°C` followed by the wind, pressure and visibility. A stale
+/// observation says how old it is, e.g. `Paris 10.03°C (as of 42 min ago)`.
+pub fn describe_current(data: &Observation, config: &Config) -> anyhow::Result<(String, String)> {
+    let values = template::values(data, &DisplayUnits::from(config))?;
+    let notification = &config.notification;
+
+    let mut summary = template::render(
+        notification.summary_template.as_deref().unwrap_or(template::DEFAULT_SUMMARY),
+        &values,
+    )
+    .context("Invalid `summary_template`")?;
+    if let Some(stale) = &data.stale {
+        summary.push_str(&format!(" (as of {})", stale.as_of));
+    }
+    let body = template::render(
+        notification.body_template.as_deref().unwrap_or_else(|| template::default_body(data)),
+        &values,
+    )
+    .context("Invalid `body_template`")?;
+
+    Ok((summary, body))
+}
+
+/// Notification icon for `weather`, from the configured icon directory or the system theme.
+fn weather_icon(weather: &WeatherEntity, config: &Config) -> String {
+    icon::resolve(icon::icon_name(weather), config.notification.icon_dir.as_deref())
+}
+
+/// Whether `weather` is severe enough for a critical notification: thunderstorms, heavy rain,
+/// freezing rain, heavy snow, volcanic ash, squalls and tornadoes.
+fn is_severe(weather: &WeatherEntity) -> bool {
+    matches!(weather.id, 200..=299 | 502..=504 | 511 | 602 | 622 | 762 | 771 | 781)
+}
+
+/// Urgency of a notification about `weather`, critical when it is severe.
+fn weather_urgency(weather: &WeatherEntity) -> Urgency {
+    if is_severe(weather) {
+        Urgency::Critical
+    } else {
+        Urgency::Normal
+    }
+}
+
+/// Show `notification` with the configured timeout, unless notifications are disabled.
+/// Returns whether it was shown.
+///
+/// On freedesktop notification servers, the notification replaces the previous one shown for
+/// `key`, e.g. `weather-Paris`, whose id is remembered in the cache directory, so that repeated
+/// runs update one notification instead of stacking new ones.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(unused_variables))]
+fn show_notification(notification: NotifyData, key: &str, config: &Config) -> anyhow::Result<bool> {
+    if !config.notification.enabled {
+        return Ok(false);
+    }
+    let mut notification = notification.with_hints(vec![
+        Hint::Category(NOTIFICATION_CATEGORY.to_string()),
+        Hint::DesktopEntry(APP_NAME.to_string()),
+    ]);
+    if let Some(timeout) = config.notification.timeout {
+        notification = notification.with_timeout(timeout);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
     {
-        let weather = &data
-            .weather
-            .as_ref()
-            .context(anyhow!("Failed to parse weather: {:?}", &data.weather))?
-            .first()
-            .context("Failed to get first weather vec item")?;
+        let path = crate::cache::dir(&config.cache).map(|dir| dir.join(notification_ids::FILE));
+        if let Some(id) = path.as_deref().and_then(|path| notification_ids::last(path, key)) {
+            notification = notification.with_replace_id(id);
+        }
+        if let (Some(path), Some(id)) = (path, notification.show()?) {
+            if let Err(err) = notification_ids::remember(&path, key, id) {
+                log::warn!("Failed to remember notification id: {err:#}");
+            }
+        }
+    }
+    // Other notification centres cannot replace a notification.
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    notification.show()?;
 
-        let weather_description =
-            format!("{}{}", &weather.description[..1].to_uppercase(), &weather.description[1..]);
-        let main = &data.main;
-        let temperature = Temperature::new(main.temp, TempUnit::Kelvin);
-        let celsius = temperature.to_celsius();
+    Ok(true)
+}
 
-        let temp = round_f32_dp(celsius.value, 2)?;
+/// Ids of the last notification shown per key, e.g. `weather-Paris`, kept in the cache directory
+/// to replace it on the next run. Only freedesktop notification servers replace notifications.
+#[cfg(all(unix, not(target_os = "macos")))]
+mod notification_ids {
+    use std::{collections::BTreeMap, path::Path};
 
-        let temp_min = Temperature::new(main.temp_min, TempUnit::Kelvin).to_celsius().value.floor();
-        let temp_max = Temperature::new(main.temp_max, TempUnit::Kelvin).to_celsius().value.ceil();
+    use crate::cache;
 
-        let unit: &str = display_tempunit!(celsius.unit);
+    /// Name of the ids file, inside the cache directory.
+    pub const FILE: &str = "notifications.json";
 
-        NotifyData::new()
-            .with_summary(format!("{query} {temp}{unit}").as_str())
-            .with_body(
-                format!("{weather_description}... {temp_min}{unit} / {temp_max}{unit}").as_str(),
-            )
-            .with_icon("weather-few-clouds") // temperature-symbolic. default: alarm
-            .show()?;
+    /// Id of the last notification shown for `key`, from the ids file at `path`.
+    pub fn last(path: &Path, key: &str) -> Option<u32> {
+        match cache::read::<BTreeMap<String, u32>>(path) {
+            Ok(ids) => ids?.value.get(key).copied(),
+            Err(err) => {
+                log::warn!("{err:#}");
+                None
+            }
+        }
     }
 
-    Ok(data)
+    /// Remember `id` as the last notification shown for `key`, in the ids file at `path`.
+    pub fn remember(path: &Path, key: &str, id: u32) -> anyhow::Result<()> {
+        let mut ids = cache::read::<BTreeMap<String, u32>>(path)
+            .ok()
+            .flatten()
+            .map(|ids| ids.value)
+            .unwrap_or_default();
+        ids.insert(key.to_string(), id);
+        cache::write(path, &ids, chrono::Utc::now().timestamp())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use pretty_assertions::assert_eq;
+
+        use super::*;
+
+        #[test]
+        fn should_remember_notification_ids_per_key() {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join(FILE);
+            assert_eq!(last(&path, "weather-Paris"), None);
+
+            remember(&path, "weather-Paris", 7).unwrap();
+            remember(&path, "weather-Lyon", 9).unwrap();
+            remember(&path, "weather-Paris", 12).unwrap();
+
+            assert_eq!(last(&path, "weather-Paris"), Some(12));
+            assert_eq!(last(&path, "weather-Lyon"), Some(9));
+        }
+    }
 }
 
-fn is_err_panic(response: &Response, query: &str) -> bool {
-    if response.status().is_client_error() {
-        let err = response.error_for_status_ref().err().unwrap().without_url();
-        panic!("{:#?}", anyhow!(err).context(format!("Failed GET request for `{query}`")));
+/// Uppercase the first letter of a weather description, e.g. `broken clouds` to `Broken clouds`
+/// or `überwiegend bewölkt` to `Überwiegend bewölkt`.
+fn capitalize(s: &str) -> String {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
     }
-    true
 }
 
 /// Define a struct `NotifyData` to store the data of the notification to be shown.
@@ -206,7 +601,13 @@ pub struct NotifyData {
     pub icon: Option<String>,
     pub appname: String,
     pub timeout: Option<u32>,
-    pub hints: Option<Vec<Hint>>,
+    // Hints, urgency and replacing are freedesktop features.
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    pub hints: Vec<Hint>,
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    pub urgency: Option<Urgency>,
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    pub replace_id: Option<u32>,
 }
 
 /// Implement a method for the `NotifyData` struct to set the summary of the notification.
@@ -218,19 +619,53 @@ impl NotifyData {
             icon: None,
             appname: APP_NAME.to_string(),
             timeout: None,
-            hints: None,
+            hints: Vec::new(),
+            urgency: None,
+            replace_id: None,
         }
     }
     /// Sends Notification to D-Bus. Show the notification with the data stored in `NotifyData`.
     ///
-    /// Returns a handle to a notification
-    pub fn show(self) -> anyhow::Result<()> {
-        Notification::new()
-            .summary(self.summary.unwrap().as_str())
-            .body(self.body.unwrap().as_str())
-            .icon(self.icon.unwrap().as_str())
-            .show()?;
-        Ok(())
+    /// Returns the id of the notification, to replace it with a later one. `None` on macOS and
+    /// Windows, which only get the summary, body, icon and timeout.
+    pub fn show(self) -> anyhow::Result<Option<u32>> {
+        let mut notification = Notification::new();
+        if !self.appname.is_empty() {
+            notification.appname(&self.appname);
+        }
+        if let Some(summary) = &self.summary {
+            notification.summary(summary);
+        }
+        if let Some(body) = &self.body {
+            notification.body(body);
+        }
+        if let Some(icon) = &self.icon {
+            notification.icon(icon);
+        }
+        if let Some(timeout) = self.timeout {
+            notification.timeout(Timeout::Milliseconds(timeout));
+        }
+        #[cfg(all(unix, not(target_os = "macos")))]
+        {
+            for hint in self.hints {
+                notification.hint(hint);
+            }
+            if let Some(urgency) = self.urgency {
+                notification.urgency(urgency);
+            }
+            if let Some(id) = self.replace_id {
+                notification.id(id);
+            }
+        }
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        let id = Some(notification.show().map_err(MausamError::Notification)?.id());
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        let id = {
+            notification.show().map_err(MausamError::Notification)?;
+            None
+        };
+        Ok(id)
     }
 
     /// Implement a method for the `NotifyData` struct to set the body of the notification.
@@ -242,8 +677,9 @@ impl NotifyData {
         self
     }
 
+    /// Add hints, e.g. [`Hint::Category`], on top of the ones already set.
     pub fn with_hints(mut self, h: Vec<Hint>) -> Self {
-        self.hints = Some(h);
+        self.hints.extend(h);
         self
     }
 
@@ -269,10 +705,26 @@ impl NotifyData {
         self
     }
 
+    /// How long the notification stays on screen, in milliseconds. `0` keeps it until
+    /// dismissed.
     pub fn with_timeout(mut self, t: u32) -> Self {
         self.timeout = Some(t);
         self
     }
+
+    /// Set the urgency. Critical notifications stay on screen until dismissed on most servers.
+    pub fn with_urgency(mut self, u: Urgency) -> Self {
+        self.urgency = Some(u);
+        self
+    }
+
+    /// Replace the notification with this id, returned by an earlier [`NotifyData::show`],
+    /// instead of showing a new one.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub fn with_replace_id(mut self, id: u32) -> Self {
+        self.replace_id = Some(id);
+        self
+    }
 }
 
 /// Convert f32 to decimal with n decimal places.
@@ -285,6 +737,7 @@ impl NotifyData {
 ///
 /// - A Result with Ok value of Decimal if the conversion is successful.
 /// - A Result with Err value of `ParseFloatError` if the conversion failed.
+///
 /// Returns a new float with the specified number of decimal points for fractional portion.
 /// Rounding currently follows "Bankers Rounding" rules. e.g. 6.5 -> 6, 7.5 -> 8
 ///
@@ -294,181 +747,45 @@ fn round_f32_dp(num: f32, dp: u32) -> anyhow::Result<f32, ParseFloatError> {
     Decimal::from_f32_retain(num).unwrap().round_dp(dp).to_string().parse::<f32>()
 }
 
-mod ipapi {
-    use reqwest::{self, Client, Response};
-    use serde::{Deserialize, Serialize};
-
-    use super::{CLIENT, IP_API_URL};
-
-    pub(crate) type ResultIpApi = anyhow::Result<IpApiResponse, reqwest::Error>;
-
-    #[derive(Deserialize, Debug, Serialize)]
-    pub(crate) struct IpApiResponse {
-        pub(crate) status: String,
-        pub(crate) country: String,
-        #[serde(rename = "countryCode")]
-        pub(crate) country_code: String,
-        pub(crate) region: String,
-        #[serde(rename = "regionName")]
-        pub(crate) region_name: String,
-        pub(crate) city: String,
-        pub(crate) zip: String,
-        pub(crate) lat: f64,
-        pub(crate) lon: f64,
-        pub(crate) timezone: String,
-        pub(crate) isp: String, // The ISP name for the location.
-        pub(crate) org: String, // The organization name for the location.
-        #[serde(rename = "as")]
-        pub(crate) as_: String,
-    }
-
-    /// `get_location` makes a GET request to the `ip-api` API and retrieves the location
-    /// information in JSON format. The JSON response is then deserialized into a struct
-    /// `IpApiResponse` using the serde crate.
-    pub(crate) async fn get_location(client: &mut Client, url: &str) -> ResultIpApi {
-        let response = client.get(url).send().await?;
-        let json = response.json::<IpApiResponse>().await?;
-        Ok(json)
-    }
-
-    /// `get_ip_api_location` fetches the current ip location.
-    ///
-    /// * Use the `CLIENT` static to make the request to the `IP_API_URL`.
-    /// * Extract the JSON from the response and parse it into an `IpApiResponse`.
-    /// * Return the parsed JSON as the result of the function.
-    pub(crate) async fn get_ip_api_location() -> ResultIpApi {
-        let response: Response = (CLIENT).get(*IP_API_URL).send().await?;
-        response.json::<IpApiResponse>().await
-    }
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quickcheck::quickcheck;
 
-    /// let location = `try_ipapi_location(client`, *`IP_API_URL).await`?;
-    /// Note that most geolocation APIs have usage limits,
-    /// so be mindful of how often you make requests to the API.
-    pub(crate) async fn try_ipapi_location(mut client: Client, url: &str) -> ResultIpApi {
-        let location = get_location(&mut client, url).await?;
-        println!("Your current location is: {location:?}");
-        Ok(location)
-    }
+    use super::*;
 
     #[test]
-    fn should_rename_fields() {
-        let api_response = IpApiResponse {
-            status: "success".to_string(),
-            country: "United States".to_string(),
-            country_code: "US".to_string(),
-            region: "CA".to_string(),
-            region_name: "California".to_string(),
-            city: "San Francisco".to_string(),
-            zip: "94107".to_string(),
-            lat: 37.7749,
-            lon: -122.4194,
-            timezone: "America/Los_Angeles".to_string(),
-            isp: "Google".to_string(),
-            org: "Google LLC".to_string(),
-            as_: "".to_string(),
-        };
-        assert_eq!(
-            serde_json::to_string(&api_response).unwrap(),
-            "{\"status\":\"success\",\"country\":\"United \
-             States\",\"countryCode\":\"US\",\"region\":\"CA\",\"regionName\":\"California\",\"\
-             city\":\"San \
-             Francisco\",\"zip\":\"94107\",\"lat\":37.7749,\"lon\":-122.4194,\"timezone\":\"\
-             America/Los_Angeles\",\"isp\":\"Google\",\"org\":\"Google LLC\",\"as\":\"\"}"
-        );
+    fn should_capitalize_unicode() {
+        assert_eq!(capitalize("broken clouds"), "Broken clouds");
+        assert_eq!(capitalize("überwiegend bewölkt"), "Überwiegend bewölkt");
+        assert_eq!(capitalize("ясно"), "Ясно");
+        assert_eq!(capitalize("छितरे हुए बादल"), "छितरे हुए बादल");
+        assert_eq!(capitalize(""), "");
     }
-}
-
-mod archive {
-    use std::net::IpAddr;
 
-    use anyhow::Result;
-    use maxminddb::geoip2;
-
-    fn maxminddb_main() -> Result<(), String> {
-        let mut args = std::env::args().skip(1);
-        let reader = maxminddb::Reader::open_readfile(
-            args.next().ok_or("First argument must be the path to the IP database")?,
-        )
-        .unwrap();
-        let ip: IpAddr = args
-            .next()
-            .ok_or("Second argument must be the IP address, like 128.101.101.101")?
-            .parse()
-            .unwrap();
-        let city: geoip2::City<'_> = reader.lookup(ip).unwrap();
-        println!("{city:#?}");
-        Ok(())
+    #[test]
+    fn test_capitalize_keeps_the_rest() {
+        fn prop(s: String) -> bool {
+            let got = capitalize(&s);
+            let rest = |s: &str| s.chars().skip(1).collect::<String>();
+            got.ends_with(&rest(&s)) && got.is_empty() == s.is_empty()
+        }
+        quickcheck(prop as fn(String) -> bool);
     }
-}
-
-mod draft {
 
-    use std::env;
-
-    use anyhow::{anyhow, Context};
-
-    use super::{
-        is_err_panic, round_f32_dp,
-        temperature::{TempUnit, Temperature},
-        NotifyData,
-    };
-    use crate::{display_tempunit, models::OpenWeatherData};
-
-    async fn fetch_weather_notify(query: &str) -> anyhow::Result<OpenWeatherData> {
-        let api_var: &str = "WEATHER_API_KEY";
-        let weather_api_key: String = match env::var(api_var) {
-            Ok(k) => k,
-            Err(err) => {
-                log::error!("Error fetching API key: {:#?}", err);
-                eprintln!("Error: {}", err);
-                std::process::exit(1);
-            }
+    #[test]
+    fn should_flag_severe_weather_as_critical() {
+        let weather = |id| WeatherEntity {
+            id,
+            main: String::new(),
+            description: String::new(),
+            icon: "01d".to_string(),
         };
-        let url = format!(
-            "https://api.openweathermap.org/data/2.5/weather?q={query}&appid={weather_api_key}"
-        );
-        let data: OpenWeatherData = reqwest::get(&url)
-            .await
-            .into_iter()
-            .find(|response| is_err_panic(response, query))
-            .unwrap()
-            .json()
-            .await
-            .map_err(|e| anyhow!("Error deserializing JSON: {}", e))?;
-        let weather = &data
-            .weather
-            .as_ref()
-            .context(anyhow!("Error parsing weather: {:?}", &data.weather))?
-            .first()
-            .context("Error getting first weather vec item")?;
-
-        let reponse_unit = TempUnit::Kelvin;
-        let temp_all = [data.main.temp, data.main.temp_min, data.main.temp_max]
-            .iter()
-            .map(|temp| {
-                let temperature = Temperature::new(*temp, reponse_unit).to_celsius();
-                let value = round_f32_dp(temperature.value, 2)?;
-                let unit = display_tempunit!(temperature.unit);
-                Ok(format!("{value}{unit}", value = value, unit = unit))
-            })
-            .collect::<anyhow::Result<Vec<_>>>()?;
-
-        let summary = format!("{query} {temp_curr}", temp_curr = temp_all[0]);
-        let weather_description = format!(
-            "{}{}... {temp_min} / {temp_max}",
-            &weather.description[..1].to_uppercase(),
-            &weather.description[1..],
-            temp_min = temp_all[1],
-            temp_max = temp_all[2]
-        );
-        let icon = "weather-few-clouds";
-
-        NotifyData::new()
-            .with_summary(&summary)
-            .with_body(&weather_description)
-            .with_icon(icon)
-            .show()?;
-
-        Ok(data)
+        for id in [200, 232, 502, 511, 602, 781] {
+            assert_eq!(weather_urgency(&weather(id)), Urgency::Critical, "{id}");
+        }
+        for id in [300, 500, 600, 741, 800, 804] {
+            assert_eq!(weather_urgency(&weather(id)), Urgency::Normal, "{id}");
+        }
     }
 }
@@ -0,0 +1,572 @@
+//! mod `alert` evaluates the `[[alerts]]` rules of the config file against an observation.
+//!
+//! A rule is a condition on the fields of `OpenWeatherData`, e.g. `temp < 0`,
+//! `wind.speed > 15 m/s`, `weather.id in 200..=232` or `humidity > 90`, and tests can be joined
+//! with `and` and `or`, `and` binding tighter. Numbers without a unit are in the display units
+//! of `--units`, so `temp < 0` is below freezing in Celsius but not in Fahrenheit.
+//!
+//! With rules configured, the weather notification only shows when a rule matches, as a critical
+//! one. Runs where nothing matches stay silent.
+
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    units::{Distance, Pressure, Speed},
+    DisplayUnits, DistanceUnit, PressureUnit, SpeedUnit, TempUnit, Temperature,
+};
+use crate::models::Observation;
+
+/// A named alert rule, e.g. `name = "Frost"` and `when = "temp < 0"`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertRule {
+    /// Shown in the notification, defaults to the condition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub when: Condition,
+}
+
+impl AlertRule {
+    /// The name of the rule, or its condition when unnamed.
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.when.source)
+    }
+}
+
+/// Labels of the `rules` matching `data`, in order.
+pub fn matching<'a>(
+    rules: &'a [AlertRule],
+    data: &Observation,
+    units: &DisplayUnits,
+) -> Vec<&'a str> {
+    rules.iter().filter(|rule| rule.when.matches(data, units)).map(AlertRule::label).collect()
+}
+
+/// Tests joined with `or` of tests joined with `and`, parsed from and written back to their
+/// source text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Condition {
+    source: String,
+    any: Vec<Vec<Test>>,
+}
+
+impl Condition {
+    /// Whether every test of any `or` branch holds for `data`. A test on a missing value, such
+    /// as the visibility some providers do not report, does not hold.
+    pub fn matches(&self, data: &Observation, units: &DisplayUnits) -> bool {
+        self.any.iter().any(|all| all.iter().all(|test| test.matches(data, units)))
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let any = split_unquoted(s, " or ")
+            .into_iter()
+            .map(|all| split_unquoted(all, " and ").into_iter().map(str::parse).collect())
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { source: s.to_string(), any })
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, String> {
+        s.parse().map_err(|err| format!("Invalid rule `{s}`: {err:#}"))
+    }
+}
+
+impl From<Condition> for String {
+    fn from(condition: Condition) -> Self {
+        condition.source
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A single comparison, e.g. `wind.speed > 15 m/s`.
+#[derive(Debug, Clone, PartialEq)]
+struct Test {
+    field: Field,
+    op: Op,
+}
+
+impl Test {
+    fn matches(&self, data: &Observation, units: &DisplayUnits) -> bool {
+        match &self.op {
+            Op::Compare(cmp, threshold) => self
+                .field
+                .number(data, threshold.unit, units)
+                .is_some_and(|value| cmp.holds(value, threshold.value)),
+            Op::In { start, end, inclusive, unit } => {
+                self.field.number(data, *unit, units).is_some_and(|value| {
+                    value >= *start && if *inclusive { value <= *end } else { value < *end }
+                })
+            }
+            Op::Text { equal, text } => self
+                .field
+                .text(data)
+                .is_some_and(|value| value.eq_ignore_ascii_case(text) == *equal),
+        }
+    }
+}
+
+impl FromStr for Test {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        let end = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'));
+        let (name, rest) = s.split_at(end.unwrap_or(s.len()));
+        let field: Field = name.parse()?;
+        let rest = rest.trim_start();
+
+        let op = if let Some(range) = rest.strip_prefix("in ") {
+            if field.kind() == Kind::Text {
+                bail!("`{name}` is text, compare it with `==` or `!=`");
+            }
+            let (numbers, unit) = split_unit(range);
+            let (start, end, inclusive) = if let Some((start, end)) = numbers.split_once("..=") {
+                (start, end, true)
+            } else {
+                let (start, end) = numbers
+                    .split_once("..")
+                    .with_context(|| format!("Expected a range like `1..=5`, got `{range}`"))?;
+                (start, end, false)
+            };
+            Op::In {
+                start: parse_number(start)?,
+                end: parse_number(end)?,
+                inclusive,
+                unit: field.unit(name, unit)?,
+            }
+        } else {
+            let cmp = ["<=", ">=", "==", "!=", "<", ">"]
+                .into_iter()
+                .find(|cmp| rest.starts_with(cmp))
+                .with_context(|| {
+                    format!("Expected `<`, `<=`, `>`, `>=`, `==`, `!=` or `in` after `{name}`")
+                })?;
+            let value = rest[cmp.len()..].trim();
+
+            if field.kind() == Kind::Text {
+                let equal = match cmp {
+                    "==" => true,
+                    "!=" => false,
+                    _ => bail!("`{name}` is text, compare it with `==` or `!=`"),
+                };
+                Op::Text { equal, text: value.trim_matches(['"', '\'']).to_string() }
+            } else {
+                let (number, unit) = split_unit(value);
+                if number.is_empty() {
+                    bail!("Expected a number, got `{value}`");
+                }
+                let cmp = match cmp {
+                    "<" => Cmp::Lt,
+                    "<=" => Cmp::Le,
+                    ">" => Cmp::Gt,
+                    ">=" => Cmp::Ge,
+                    "==" => Cmp::Eq,
+                    _ => Cmp::Ne,
+                };
+                Op::Compare(
+                    cmp,
+                    Threshold { value: parse_number(number)?, unit: field.unit(name, unit)? },
+                )
+            }
+        };
+
+        Ok(Self { field, op })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Compare(Cmp, Threshold),
+    /// `start..end`, or `start..=end` when `inclusive`.
+    In {
+        start: f32,
+        end: f32,
+        inclusive: bool,
+        unit: Option<Unit>,
+    },
+    /// Case insensitive `==` when `equal`, `!=` otherwise.
+    Text {
+        equal: bool,
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Cmp {
+    fn holds(self, value: f32, threshold: f32) -> bool {
+        // Conversions between units are not exact, `10 km` of visibility is `10000 m`.
+        let equal = (value - threshold).abs() <= 1e-5 * value.abs().max(threshold.abs()).max(1.0);
+        match self {
+            Self::Lt => value < threshold,
+            Self::Le => value < threshold || equal,
+            Self::Gt => value > threshold,
+            Self::Ge => value > threshold || equal,
+            Self::Eq => equal,
+            Self::Ne => !equal,
+        }
+    }
+}
+
+/// A number to compare with, in `unit` or the display unit of the field when `None`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Threshold {
+    value: f32,
+    unit: Option<Unit>,
+}
+
+/// Unit written after a number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    Temp(TempUnit),
+    Speed(SpeedUnit),
+    Pressure(PressureUnit),
+    Distance(DistanceUnit),
+    Percent,
+}
+
+impl FromStr for Unit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(match s.to_lowercase().as_str() {
+            "°c" | "c" => Self::Temp(TempUnit::Celsius),
+            "°f" | "f" => Self::Temp(TempUnit::Fahrenheit),
+            "k" => Self::Temp(TempUnit::Kelvin),
+            "m/s" => Self::Speed(SpeedUnit::Ms),
+            "km/h" => Self::Speed(SpeedUnit::Kmh),
+            "mph" => Self::Speed(SpeedUnit::Mph),
+            "kn" | "knots" => Self::Speed(SpeedUnit::Knots),
+            "hpa" => Self::Pressure(PressureUnit::Hpa),
+            "inhg" => Self::Pressure(PressureUnit::Inhg),
+            "mmhg" => Self::Pressure(PressureUnit::Mmhg),
+            "m" => Self::Distance(DistanceUnit::M),
+            "km" => Self::Distance(DistanceUnit::Km),
+            "mi" => Self::Distance(DistanceUnit::Mi),
+            "%" => Self::Percent,
+            _ => bail!("Unknown unit `{s}`"),
+        })
+    }
+}
+
+/// What a field measures, which decides the units it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Temp,
+    Speed,
+    Pressure,
+    Distance,
+    Percent,
+    Plain,
+    Text,
+}
+
+/// A field of `OpenWeatherData` a rule can test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Temp,
+    FeelsLike,
+    TempMin,
+    TempMax,
+    Pressure,
+    Humidity,
+    WindSpeed,
+    WindDeg,
+    Clouds,
+    Visibility,
+    WeatherId,
+    WeatherMain,
+    WeatherDescription,
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        // `main.temp` and `temp` are the same field.
+        Ok(match s.strip_prefix("main.").unwrap_or(s) {
+            "temp" => Self::Temp,
+            "feels_like" => Self::FeelsLike,
+            "temp_min" => Self::TempMin,
+            "temp_max" => Self::TempMax,
+            "pressure" => Self::Pressure,
+            "humidity" => Self::Humidity,
+            "wind.speed" => Self::WindSpeed,
+            "wind.deg" => Self::WindDeg,
+            "clouds" | "clouds.all" => Self::Clouds,
+            "visibility" => Self::Visibility,
+            "weather.id" => Self::WeatherId,
+            "weather.main" => Self::WeatherMain,
+            "weather.description" => Self::WeatherDescription,
+            _ => bail!(
+                "Unknown field `{s}`, expected one of: temp, feels_like, temp_min, temp_max, \
+                 pressure, humidity, wind.speed, wind.deg, clouds, visibility, weather.id, \
+                 weather.main, weather.description"
+            ),
+        })
+    }
+}
+
+impl Field {
+    fn kind(self) -> Kind {
+        match self {
+            Self::Temp | Self::FeelsLike | Self::TempMin | Self::TempMax => Kind::Temp,
+            Self::Pressure => Kind::Pressure,
+            Self::WindSpeed => Kind::Speed,
+            Self::Visibility => Kind::Distance,
+            Self::Humidity | Self::Clouds => Kind::Percent,
+            Self::WindDeg | Self::WeatherId => Kind::Plain,
+            Self::WeatherMain | Self::WeatherDescription => Kind::Text,
+        }
+    }
+
+    /// The unit written after a number for this field, checked against what it measures.
+    fn unit(self, name: &str, unit: &str) -> anyhow::Result<Option<Unit>> {
+        if unit.is_empty() {
+            return Ok(None);
+        }
+        let parsed: Unit = unit.parse()?;
+        let fits = matches!(
+            (self.kind(), parsed),
+            (Kind::Temp, Unit::Temp(_))
+                | (Kind::Speed, Unit::Speed(_))
+                | (Kind::Pressure, Unit::Pressure(_))
+                | (Kind::Distance, Unit::Distance(_))
+                | (Kind::Percent, Unit::Percent)
+        );
+        if !fits {
+            bail!("Unit `{unit}` does not fit `{name}`");
+        }
+        Ok(Some(parsed))
+    }
+
+    /// The value of the field in `unit`, or in its display unit in `units`.
+    #[allow(clippy::cast_precision_loss)]
+    fn number(self, data: &Observation, unit: Option<Unit>, units: &DisplayUnits) -> Option<f32> {
+        let main = &data.main;
+        let temp = |kelvin: f32| {
+            let unit = match unit {
+                Some(Unit::Temp(unit)) => unit,
+                _ => units.temp,
+            };
+            Temperature::new(kelvin, TempUnit::Kelvin).to_unit(unit).value
+        };
+
+        match self {
+            Self::Temp => Some(temp(main.temp)),
+            Self::FeelsLike => Some(temp(main.feels_like)),
+            Self::TempMin => Some(temp(main.temp_min)),
+            Self::TempMax => Some(temp(main.temp_max)),
+            Self::Pressure => {
+                let unit = match unit {
+                    Some(Unit::Pressure(unit)) => unit,
+                    _ => units.pressure,
+                };
+                Some(Pressure::new(main.pressure as f32, PressureUnit::Hpa).to_unit(unit).value)
+            }
+            Self::WindSpeed => {
+                let unit = match unit {
+                    Some(Unit::Speed(unit)) => unit,
+                    _ => units.speed,
+                };
+                Some(Speed::new(data.wind.speed, SpeedUnit::Ms).to_unit(unit).value)
+            }
+            Self::Visibility => {
+                let unit = match unit {
+                    Some(Unit::Distance(unit)) => unit,
+                    _ => units.distance,
+                };
+                data.visibility
+                    .map(|metres| Distance::new(metres as f32, DistanceUnit::M).to_unit(unit).value)
+            }
+            Self::Humidity => Some(main.humidity as f32),
+            Self::Clouds => Some(data.clouds.all as f32),
+            Self::WindDeg => Some(data.wind.deg as f32),
+            Self::WeatherId => Some(data.weather.id as f32),
+            Self::WeatherMain | Self::WeatherDescription => None,
+        }
+    }
+
+    fn text(self, data: &Observation) -> Option<&str> {
+        match self {
+            Self::WeatherMain => Some(&data.weather.main),
+            Self::WeatherDescription => Some(&data.weather.description),
+            _ => None,
+        }
+    }
+}
+
+/// Split `s` into its leading number, or range of numbers, and the unit after it, e.g. `15` and
+/// `m/s` for `15 m/s`.
+fn split_unit(s: &str) -> (&str, &str) {
+    let s = s.trim();
+    let end = s.find(|c: char| !(c.is_ascii_digit() || "+-.=".contains(c))).unwrap_or(s.len());
+    (s[..end].trim(), s[end..].trim())
+}
+
+fn parse_number(s: &str) -> anyhow::Result<f32> {
+    s.trim().parse().with_context(|| format!("Expected a number, got `{s}`"))
+}
+
+/// Split `s` at every `separator` outside of quotes, so that `"light rain and snow"` stays one
+/// text.
+fn split_unquoted<'a>(s: &'a str, separator: &str) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        if let Some(open) = quote {
+            if c == open {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        } else if i >= start && s[i..].starts_with(separator) {
+            // Only past the end of the last separator, which may start with the same characters.
+            parts.push(&s[start..i]);
+            start = i + separator.len();
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        config::Units,
+        models::{Observation, OpenWeatherData},
+    };
+
+    /// London at 7.19°C, 86% humidity, 1021 hPa, 6.17 m/s wind from 300° and broken clouds.
+    fn london() -> Observation {
+        Observation::try_from(OpenWeatherData::default()).unwrap()
+    }
+
+    fn holds(condition: &str, units: Units) -> bool {
+        let condition: Condition = condition.parse().unwrap();
+        condition.matches(&london(), &DisplayUnits::from(units))
+    }
+
+    #[test]
+    fn should_compare_in_display_units() {
+        assert!(holds("temp > 7", Units::Metric));
+        assert!(!holds("temp < 0", Units::Metric));
+        assert!(holds("temp > 44", Units::Imperial));
+        assert!(holds("temp > 280", Units::Standard));
+        assert!(holds("wind.speed > 13 mph", Units::Metric));
+        assert!(!holds("wind.speed > 15 m/s", Units::Imperial));
+        assert!(holds("main.temp <= 45 °F", Units::Metric));
+        assert!(holds("pressure >= 30 inHg", Units::Metric));
+        assert!(holds("visibility == 10 km", Units::Metric));
+        assert!(holds("wind.deg == 300 and wind.deg != 301", Units::Metric));
+    }
+
+    #[test]
+    fn should_match_ranges_text_and_all_tests() {
+        assert!(holds("weather.id in 801..=804", Units::Metric));
+        assert!(!holds("weather.id in 200..=232", Units::Metric));
+        assert!(!holds("weather.id in 800..803", Units::Metric));
+        assert!(holds("temp in -5..10 °C", Units::Imperial));
+        assert!(holds("weather.main == clouds", Units::Metric));
+        assert!(holds("weather.description != \"light rain\"", Units::Metric));
+        assert!(holds("humidity > 80 % and clouds >= 75", Units::Metric));
+        assert!(!holds("humidity > 90 and clouds >= 75", Units::Metric));
+        assert!(holds("temp < 0 or humidity > 80 and clouds >= 75", Units::Metric));
+        assert!(!holds("temp < 0 and humidity > 80 or clouds > 75", Units::Metric));
+    }
+
+    #[test]
+    fn should_keep_quoted_and_or_in_text() {
+        let condition: Condition =
+            "weather.description == \"light rain and snow\"".parse().unwrap();
+        assert_eq!(condition.any.len(), 1);
+        assert_eq!(
+            condition.any[0],
+            [Test {
+                field: Field::WeatherDescription,
+                op: Op::Text { equal: true, text: "light rain and snow".to_string() },
+            }]
+        );
+
+        assert!(holds(
+            "weather.description != \"light rain and snow\" and temp > 7",
+            Units::Metric
+        ));
+        assert!(holds("weather.main == 'snow or rain' or clouds >= 75", Units::Metric));
+        assert!(!holds("weather.main == 'snow or rain' or clouds > 90", Units::Metric));
+    }
+
+    #[test]
+    fn should_reject_invalid_rules() {
+        for (rule, error) in [
+            ("rain > 1", "Unknown field `rain`"),
+            ("temp ~ 1", "Expected `<`"),
+            ("temp < cold", "Expected a number, got `cold`"),
+            ("temp < 1 m/s", "Unit `m/s` does not fit `temp`"),
+            ("humidity < 1 parsec", "Unknown unit `parsec`"),
+            ("weather.id in 200", "Expected a range"),
+            ("weather.main > Rain", "compare it with `==` or `!=`"),
+            ("weather.main in 1..5", "compare it with `==` or `!=`"),
+            ("weather.description in 200..=232", "`weather.description` is text"),
+        ] {
+            let err = format!("{:#}", rule.parse::<Condition>().unwrap_err());
+            assert!(err.contains(error), "{rule}: {err}");
+        }
+    }
+
+    #[test]
+    fn should_label_matching_rules() {
+        #[derive(Deserialize)]
+        struct Rules {
+            alerts: Vec<AlertRule>,
+        }
+        let rules: Rules = toml::from_str(
+            r#"
+[[alerts]]
+name = "Cloudy"
+when = "clouds > 50"
+
+[[alerts]]
+when = "temp < 0"
+
+[[alerts]]
+when = "humidity > 80"
+"#,
+        )
+        .unwrap();
+
+        let got = matching(&rules.alerts, &london(), &DisplayUnits::from(Units::Metric));
+        assert_eq!(got, ["Cloudy", "humidity > 80"]);
+
+        let err = toml::from_str::<AlertRule>("when = \"temp <\"").unwrap_err().to_string();
+        assert!(err.contains("Invalid rule `temp <`"), "{err}");
+    }
+}
@@ -0,0 +1,268 @@
+//! mod `changes` compares the current weather with the last notified one, for `--changes`.
+//!
+//! The last notified observation of each location is kept in the cache directory, so that slow
+//! drifts add up until they are worth a notification. One is only worth it when the temperature
+//! moved by at least `changes.temp` degrees, the condition group changed, e.g. from `Clouds` to
+//! `Rain`, or the pressure dropped by at least `changes.pressure_drop` hPa per hour, a sign of a
+//! coming storm. The pressure trend is measured since the last run instead, so that a fast drop
+//! after a quiet day is not averaged away.
+
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{capitalize, units::Pressure, DisplayUnits, PressureUnit, TempUnit, Temperature};
+use crate::{cache, config::Config, display_tempunit, models::Observation};
+
+/// Name of the file keeping the observations to compare with per location, inside the cache
+/// directory.
+const STATE_FILE: &str = "observations.json";
+
+/// The observations of a location the next run compares with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Baseline {
+    /// The last notified observation, for the temperature and the condition.
+    notified: Option<Observation>,
+    /// The observation of the last run, for the pressure trend.
+    last_run: Option<Observation>,
+}
+
+/// The changes in `data` since the last notification for the location `key`, then remember
+/// `data` as the last run's. `None` when there is nothing to compare with, before the first
+/// notification or without a cache directory.
+///
+/// A stale observation, served from the cache, is no news: it has no changes and is not
+/// remembered.
+pub fn since_last_notified(data: &Observation, key: &str, config: &Config) -> Option<Vec<String>> {
+    if data.stale.is_some() {
+        return Some(Vec::new());
+    }
+    let path = cache::dir(&config.cache)?.join(STATE_FILE);
+    let mut state = read_state(&path);
+    let baseline = state.entry(key.to_string()).or_default();
+
+    let changes = baseline.notified.as_ref().map(|notified| {
+        let last_run = baseline.last_run.as_ref().unwrap_or(notified);
+        changes(notified, last_run, data, config, &DisplayUnits::from(config))
+    });
+    baseline.last_run = Some(data.clone());
+    write_state(&path, &state, key);
+
+    changes
+}
+
+/// Remember `data` as the last notified observation for the location `key`, the one the next
+/// runs compare with, once its notification was shown. Nothing is remembered without change
+/// detection or for a stale observation.
+pub fn remember(data: &Observation, key: &str, config: &Config) {
+    if !config.changes.enabled || data.stale.is_some() {
+        return;
+    }
+    let Some(path) = cache::dir(&config.cache).map(|dir| dir.join(STATE_FILE)) else {
+        return;
+    };
+    let mut state = read_state(&path);
+    state.entry(key.to_string()).or_default().notified = Some(data.clone());
+    write_state(&path, &state, key);
+}
+
+/// The baselines per location, empty when the file is missing or unreadable.
+fn read_state(path: &Path) -> BTreeMap<String, Baseline> {
+    match cache::read::<BTreeMap<String, Baseline>>(path) {
+        Ok(state) => state.map(|state| state.value).unwrap_or_default(),
+        Err(err) => {
+            log::warn!("{err:#}");
+            BTreeMap::new()
+        }
+    }
+}
+
+fn write_state(path: &Path, state: &BTreeMap<String, Baseline>, key: &str) {
+    if let Err(err) = cache::write(path, state, chrono::Utc::now().timestamp()) {
+        log::warn!("Failed to remember the weather for `{key}`: {err:#}");
+    }
+}
+
+/// Meaningful changes in `current`, e.g. `Rain, was clouds` or `Temperature down 3.5°C`. The
+/// temperature and condition are compared with the `notified` observation, the pressure trend
+/// with the `last_run` one.
+pub fn changes(
+    notified: &Observation,
+    last_run: &Observation,
+    current: &Observation,
+    config: &Config,
+    units: &DisplayUnits,
+) -> Vec<String> {
+    let mut changes = Vec::new();
+    let thresholds = &config.changes;
+
+    if thresholds.condition && notified.weather.main != current.weather.main {
+        changes.push(format!(
+            "{}, was {}",
+            capitalize(&current.weather.description),
+            notified.weather.main.to_lowercase()
+        ));
+    }
+
+    let temp = |kelvin: f32| Temperature::new(kelvin, TempUnit::Kelvin).to_unit(units.temp).value;
+    let delta = temp(current.main.temp) - temp(notified.main.temp);
+    if delta.abs() >= thresholds.temp {
+        changes.push(format!(
+            "Temperature {} {}{}",
+            if delta > 0.0 { "up" } else { "down" },
+            units.number(format!("{:.1}", delta.abs())),
+            display_tempunit!(units.temp)
+        ));
+    }
+
+    // Per hour, over at least an hour, so that a drop of 1 hPa in ten minutes is no storm.
+    #[allow(clippy::cast_precision_loss)]
+    let hours = ((current.dt - last_run.dt) as f32 / 3600.0).max(1.0);
+    #[allow(clippy::cast_precision_loss)]
+    let drop = (last_run.main.pressure - current.main.pressure) as f32 / hours;
+    if current.dt > last_run.dt && drop >= thresholds.pressure_drop {
+        let decimals = units.pressure.decimals() + 1;
+        let rate = Pressure::new(drop, PressureUnit::Hpa).to_unit(units.pressure);
+        changes.push(format!(
+            "Pressure falling {} {}/h",
+            units.number(format!("{:.decimals$}", rate.value)),
+            rate.unit
+        ));
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        config::{ChangesConfig, Units},
+        models::{OpenWeatherData, Stale},
+    };
+
+    /// London at 7.19°C, 1021 hPa and broken clouds.
+    fn london() -> Observation {
+        Observation::try_from(OpenWeatherData::default()).unwrap()
+    }
+
+    fn changed(previous: &Observation, current: &Observation) -> Vec<String> {
+        let units = DisplayUnits::from(Units::Metric);
+        changes(previous, previous, current, &Config::default(), &units)
+    }
+
+    /// Change detection remembering in `dir`.
+    fn detecting(dir: &Path) -> Config {
+        let mut config = Config::default();
+        config.changes.enabled = true;
+        config.cache.dir = Some(dir.to_path_buf());
+        config
+    }
+
+    #[test]
+    fn should_ignore_small_changes() {
+        let previous = london();
+        assert_eq!(changed(&previous, &previous), Vec::<String>::new());
+
+        let mut current = london();
+        current.dt += 3600;
+        current.main.temp += 2.9;
+        current.main.pressure -= 1;
+        current.weather.description = "overcast clouds".to_string();
+        current.weather.id = 804;
+        assert_eq!(changed(&previous, &current), Vec::<String>::new());
+    }
+
+    #[test]
+    fn should_report_meaningful_changes() {
+        let previous = london();
+        let mut current = london();
+        current.dt += 2 * 3600;
+        current.main.temp -= 3.5;
+        current.main.pressure -= 5;
+        current.weather.main = "Rain".to_string();
+        current.weather.description = "light rain".to_string();
+
+        assert_eq!(
+            changed(&previous, &current),
+            ["Light rain, was clouds", "Temperature down 3.5°C", "Pressure falling 2.5 hPa/h"]
+        );
+
+        let config = Config {
+            changes: ChangesConfig { condition: false, temp: 10.0, ..ChangesConfig::default() },
+            lang: Some("de".to_string()),
+            ..Config::default()
+        };
+        let got = changes(&previous, &previous, &current, &config, &DisplayUnits::from(&config));
+        assert_eq!(got, ["Pressure falling 2,5 hPa/h"]);
+    }
+
+    #[test]
+    fn should_remember_the_last_notified_observation_per_location() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = detecting(dir.path());
+
+        let paris = london();
+        let mut rainy = london();
+        rainy.weather.main = "Rain".to_string();
+        rainy.weather.description = "light rain".to_string();
+
+        assert_eq!(since_last_notified(&paris, "paris", &config), None);
+        remember(&paris, "paris", &config);
+        assert_eq!(since_last_notified(&rainy, "london", &config), None);
+        assert_eq!(since_last_notified(&paris, "paris", &config), Some(Vec::new()));
+        let got = since_last_notified(&rainy, "paris", &config).unwrap();
+        assert_eq!(got, ["Light rain, was clouds"]);
+
+        rainy.stale = Some(Stale::new(0, 60));
+        assert_eq!(since_last_notified(&rainy, "rome", &config), Some(Vec::new()));
+        remember(&rainy, "rome", &config);
+        rainy.stale = None;
+        assert_eq!(since_last_notified(&rainy, "rome", &config), None);
+
+        let disabled = Config { changes: ChangesConfig::default(), ..config.clone() };
+        remember(&rainy, "rome", &disabled);
+        assert_eq!(since_last_notified(&rainy, "rome", &config), None);
+    }
+
+    #[test]
+    fn should_add_up_small_changes_until_notified() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = detecting(dir.path());
+
+        let mut data = london();
+        remember(&data, "london", &config);
+        for _ in 0..2 {
+            data.dt += 3600;
+            data.main.temp += 1.2;
+            assert_eq!(since_last_notified(&data, "london", &config), Some(Vec::new()));
+        }
+        data.dt += 3600;
+        data.main.temp += 1.2;
+        let got = since_last_notified(&data, "london", &config).unwrap();
+        assert_eq!(got, ["Temperature up 3.6°C"]);
+
+        remember(&data, "london", &config);
+        assert_eq!(since_last_notified(&data, "london", &config), Some(Vec::new()));
+    }
+
+    #[test]
+    fn should_report_a_fast_pressure_drop_after_a_quiet_spell() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = detecting(dir.path());
+
+        let mut data = london();
+        remember(&data, "london", &config);
+        for _ in 0..24 {
+            data.dt += 3600;
+            assert_eq!(since_last_notified(&data, "london", &config), Some(Vec::new()));
+        }
+        data.dt += 3600;
+        data.main.pressure -= 5;
+        let got = since_last_notified(&data, "london", &config).unwrap();
+        assert_eq!(got, ["Pressure falling 5.0 hPa/h"]);
+    }
+}
@@ -0,0 +1,281 @@
+//! mod `forecast` condenses a provider [`Forecast`] into one summary per day, or lists the
+//! upcoming slots within a horizon.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use serde::Serialize;
+
+use super::{capitalize, round_f32_dp, DisplayUnits, TempUnit, Temperature};
+use crate::{
+    display_tempunit,
+    models::{Forecast, WeatherEntity, Wind},
+};
+
+/// Slot length assumed when a forecast has a single slot, in seconds.
+const DEFAULT_SLOT_SECS: i64 = 3600;
+
+/// Summary of all forecast slots that fall on one local calendar day.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DaySummary {
+    pub date: NaiveDate,
+    /// Lowest `temp_min` of the day, in Kelvin.
+    pub temp_min: f32,
+    /// Highest `temp_max` of the day, in Kelvin.
+    pub temp_max: f32,
+    /// The condition reported by the most slots of the day.
+    pub weather: WeatherEntity,
+}
+
+impl DaySummary {
+    /// One line human summary in `unit`, e.g. `Thu 02 Feb  8°C / 11°C  Overcast clouds`.
+    pub fn describe(&self, unit: TempUnit) -> String {
+        let temp_min = Temperature::new(self.temp_min, TempUnit::Kelvin).to_unit(unit);
+        let temp_max = Temperature::new(self.temp_max, TempUnit::Kelvin).to_unit(unit);
+        let unit = display_tempunit!(temp_min.unit);
+
+        format!(
+            "{}  {}{unit} / {}{unit}  {}",
+            self.date.format("%a %d %b"),
+            temp_min.value.floor(),
+            temp_max.value.ceil(),
+            capitalize(&self.weather.description),
+        )
+    }
+}
+
+/// Group the forecast slots by local calendar day, in the forecast's own timezone.
+pub fn summarize_days(forecast: &Forecast) -> Vec<DaySummary> {
+    let mut days: Vec<(DaySummary, Vec<(&WeatherEntity, usize)>)> = Vec::new();
+
+    for slot in &forecast.slots {
+        let Some(date) = DateTime::from_timestamp(slot.dt + i64::from(forecast.timezone), 0)
+            .map(|dt| dt.date_naive())
+        else {
+            continue;
+        };
+
+        match days.last_mut() {
+            Some((day, counts)) if day.date == date => {
+                day.temp_min = day.temp_min.min(slot.main.temp_min);
+                day.temp_max = day.temp_max.max(slot.main.temp_max);
+                match counts.iter_mut().find(|(w, _)| w.main == slot.weather.main) {
+                    Some((_, n)) => *n += 1,
+                    None => counts.push((&slot.weather, 1)),
+                }
+            }
+            _ => days.push((
+                DaySummary {
+                    date,
+                    temp_min: slot.main.temp_min,
+                    temp_max: slot.main.temp_max,
+                    weather: slot.weather.clone(),
+                },
+                vec![(&slot.weather, 1)],
+            )),
+        }
+    }
+
+    days.into_iter()
+        .map(|(mut day, counts)| {
+            // `max_by_key` keeps the last maximum, so reverse to prefer the earliest condition.
+            if let Some((weather, _)) = counts.iter().rev().max_by_key(|(_, n)| *n) {
+                day.weather = (*weather).clone();
+            }
+            day
+        })
+        .collect()
+}
+
+/// One upcoming forecast slot, hourly or 3-hourly depending on the provider.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct HourSummary {
+    /// Start of the slot, in the forecast's local time.
+    pub time: NaiveDateTime,
+    /// Temperature, in Kelvin.
+    pub temp: f32,
+    /// Probability of precipitation, from `0.0` to `1.0`.
+    pub pop: Option<f32>,
+    /// Precipitation volume for the slot, in mm.
+    pub precipitation: Option<f32>,
+    pub wind: Wind,
+    pub weather: WeatherEntity,
+}
+
+impl HourSummary {
+    /// One line human summary in `units`, e.g.
+    /// `Thu 02 Feb 15:00  8.05°C  42% 1.2 mm  4.2 m/s 250°  Light rain`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the temperature cannot be rounded.
+    pub fn describe(&self, units: &DisplayUnits) -> anyhow::Result<String> {
+        let temperature = Temperature::new(self.temp, TempUnit::Kelvin).to_unit(units.temp);
+        let temp = round_f32_dp(temperature.value, 2)?;
+        let unit = display_tempunit!(temperature.unit);
+        let mut pop = self.pop.map_or_else(|| "-".to_string(), |p| format!("{:.0}%", p * 100.0));
+        if let Some(precipitation) = self.precipitation.filter(|p| *p > 0.0) {
+            pop.push_str(&format!(" {}", units.precipitation(precipitation)));
+        }
+
+        Ok(format!(
+            "{}  {}{unit}  {pop}  {} {}°  {}",
+            self.time.format("%a %d %b %H:%M"),
+            units.number(temp),
+            units.speed(self.wind.speed),
+            self.wind.deg,
+            capitalize(&self.weather.description),
+        ))
+    }
+}
+
+/// List the slots that have not ended by `now` and start within the next `hours`.
+///
+/// `now` is a Unix timestamp in seconds.
+pub fn upcoming_slots(forecast: &Forecast, now: i64, hours: u32) -> Vec<HourSummary> {
+    let slot_secs = match forecast.slots.as_slice() {
+        [first, second, ..] => second.dt - first.dt,
+        _ => DEFAULT_SLOT_SECS,
+    };
+    let horizon = now + i64::from(hours) * 3600;
+
+    forecast
+        .slots
+        .iter()
+        .filter(|slot| slot.dt + slot_secs > now && slot.dt < horizon)
+        .filter_map(|slot| {
+            let time = DateTime::from_timestamp(slot.dt + i64::from(forecast.timezone), 0)?;
+            Some(HourSummary {
+                time: time.naive_utc(),
+                temp: slot.main.temp,
+                pop: slot.pop,
+                precipitation: slot.precipitation,
+                wind: slot.wind.clone(),
+                weather: slot.weather.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        config::Units,
+        models::{Clouds, Coord, ForecastSlot, Main},
+    };
+
+    fn slot(dt: i64, temp: f32, main: &str) -> ForecastSlot {
+        ForecastSlot {
+            dt,
+            weather: WeatherEntity {
+                id: 800,
+                main: main.to_string(),
+                description: main.to_lowercase(),
+                icon: "01d".to_string(),
+            },
+            main: Main {
+                temp,
+                feels_like: temp,
+                temp_min: temp,
+                temp_max: temp,
+                pressure: 1013,
+                humidity: 50,
+            },
+            wind: Wind { speed: 1.0, deg: 0 },
+            clouds: Clouds { all: 0 },
+            pop: None,
+            precipitation: None,
+        }
+    }
+
+    #[test]
+    fn should_group_slots_by_local_day() {
+        // 2023-02-02 21:00 UTC is already 2023-02-03 in UTC+5.
+        let forecast = Forecast {
+            name: "Test".to_string(),
+            country: None,
+            coord: Coord { lon: 0.0, lat: 0.0 },
+            timezone: 5 * 3600,
+            slots: vec![
+                slot(1675339200, 280.0, "Clouds"), // 2023-02-02 12:00 UTC
+                slot(1675350000, 284.0, "Rain"),   // 2023-02-02 15:00 UTC
+                slot(1675360800, 282.0, "Rain"),   // 2023-02-02 18:00 UTC
+                slot(1675371600, 275.0, "Clear"),  // 2023-02-02 21:00 UTC
+            ],
+            stale: None,
+        };
+
+        let days = summarize_days(&forecast);
+
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].date, NaiveDate::from_ymd_opt(2023, 2, 2).unwrap());
+        assert_eq!(days[0].temp_min, 280.0);
+        assert_eq!(days[0].temp_max, 284.0);
+        assert_eq!(days[0].weather.main, "Rain");
+        assert_eq!(days[1].date, NaiveDate::from_ymd_opt(2023, 2, 3).unwrap());
+        assert_eq!(days[1].weather.main, "Clear");
+    }
+
+    #[test]
+    fn should_prefer_earliest_condition_on_tie() {
+        let forecast = Forecast {
+            name: "Test".to_string(),
+            country: None,
+            coord: Coord { lon: 0.0, lat: 0.0 },
+            timezone: 0,
+            slots: vec![slot(1675339200, 280.0, "Clouds"), slot(1675350000, 280.0, "Rain")],
+            stale: None,
+        };
+
+        assert_eq!(summarize_days(&forecast)[0].weather.main, "Clouds");
+    }
+
+    #[test]
+    fn should_list_slots_within_horizon() {
+        let hour = 3600;
+        let start = 1675339200; // 2023-02-02 12:00 UTC
+        let forecast = Forecast {
+            name: "Test".to_string(),
+            country: None,
+            coord: Coord { lon: 0.0, lat: 0.0 },
+            timezone: 3600,
+            slots: (0..8).map(|i| slot(start + i * 3 * hour, 280.0, "Clouds")).collect(),
+            stale: None,
+        };
+
+        // Half way through the first slot, which is still shown.
+        let now = start + 90 * 60;
+        let got = upcoming_slots(&forecast, now, 6);
+
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[0].time.format("%H:%M").to_string(), "13:00");
+        assert_eq!(got[2].time.format("%H:%M").to_string(), "19:00");
+    }
+
+    #[test]
+    fn should_describe_hour() {
+        let summary = HourSummary {
+            time: DateTime::from_timestamp(1675350000, 0).unwrap().naive_utc(),
+            temp: 281.2,
+            pop: Some(0.42),
+            precipitation: Some(1.2),
+            wind: Wind { speed: 4.25, deg: 250 },
+            weather: WeatherEntity {
+                id: 500,
+                main: "Rain".to_string(),
+                description: "light rain".to_string(),
+                icon: "10d".to_string(),
+            },
+        };
+
+        assert_eq!(
+            summary.describe(&DisplayUnits::from(Units::Metric)).unwrap(),
+            "Thu 02 Feb 15:00  8.05°C  42% 1.2 mm  4.2 m/s 250°  Light rain"
+        );
+        assert_eq!(
+            summary.describe(&DisplayUnits::from(Units::Imperial)).unwrap(),
+            "Thu 02 Feb 15:00  46.49°F  42% 0.05 in  9.5 mph 250°  Light rain"
+        );
+    }
+}
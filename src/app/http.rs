@@ -0,0 +1,250 @@
+//! mod `http` wraps the `reqwest` client shared by the providers with timeouts and retries.
+//!
+//! A request that fails with a network error or a 5xx status is retried after a jittered,
+//! exponentially growing delay. A 429 status waits for as long as its `Retry-After` header
+//! asks, unless that is longer than `http.max_backoff`. A laptop waking from suspend often
+//! has no network for the first few seconds, which these retries ride out.
+
+use std::{
+    hash::{BuildHasher, RandomState},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Client, IntoUrl, RequestBuilder, Response, StatusCode,
+};
+
+use crate::config::HttpConfig;
+
+/// HTTP client with timeouts and a retry policy.
+#[derive(Debug, Clone)]
+pub struct Http {
+    client: Client,
+    retries: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Http {
+    /// Build the client from `config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the TLS backend cannot be initialized.
+    pub fn new(config: &HttpConfig) -> anyhow::Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_millis(config.connect_timeout))
+            .timeout(Duration::from_millis(config.response_timeout))
+            .build()?;
+
+        Ok(Self {
+            client,
+            retries: config.retries,
+            backoff: Duration::from_millis(config.backoff),
+            max_backoff: Duration::from_millis(config.max_backoff),
+        })
+    }
+
+    /// Start a GET request to `url`, to be sent with [`Http::send`].
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Send `request`, retrying network errors, 5xx and 429 statuses.
+    ///
+    /// The last response is returned as is once the retries are used up, so callers still see
+    /// its status.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the last attempt if no attempt got a response.
+    pub async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            // Requests with a streaming body cannot be cloned, nor retried.
+            let Some(next) = request.try_clone() else {
+                return request.send().await;
+            };
+            let result = next.send().await;
+
+            let Some(wait) = self.retry_delay(&result, attempt) else {
+                return result;
+            };
+            match &result {
+                Ok(response) => log::warn!("Got `{}`, retrying in {wait:?}", response.status()),
+                Err(err) => log::warn!("Request failed: {err}, retrying in {wait:?}"),
+            }
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
+    }
+
+    /// How long to wait before retrying `result`, or `None` to give up.
+    fn retry_delay(&self, result: &reqwest::Result<Response>, attempt: u32) -> Option<Duration> {
+        if attempt >= self.retries {
+            return None;
+        }
+        match result {
+            Ok(response) if response.status() == StatusCode::TOO_MANY_REQUESTS => {
+                match retry_after(response.headers(), Utc::now()) {
+                    Some(wait) => (wait <= self.max_backoff).then_some(wait),
+                    None => Some(self.backoff(attempt)),
+                }
+            }
+            Ok(response) if response.status().is_server_error() => Some(self.backoff(attempt)),
+            Err(err) if err.is_connect() || err.is_timeout() || err.is_request() => {
+                Some(self.backoff(attempt))
+            }
+            _ => None,
+        }
+    }
+
+    /// Delay before retry number `attempt + 1`: the base backoff doubled on each attempt and
+    /// capped at `max_backoff`, of which a random half is dropped so clients spread out.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let cap = self.backoff.saturating_mul(2_u32.saturating_pow(attempt)).min(self.max_backoff);
+        #[allow(clippy::cast_precision_loss)]
+        let random = RandomState::new().hash_one(attempt) as f64 / u64::MAX as f64;
+        cap / 2 + (cap / 2).mul_f64(random)
+    }
+}
+
+/// The delay asked for by a `Retry-After` header, in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - now).to_std().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderValue;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    fn http(retries: u32) -> Http {
+        let config = HttpConfig {
+            connect_timeout: 1000,
+            response_timeout: 200,
+            retries,
+            backoff: 1,
+            max_backoff: 1000,
+        };
+        Http::new(&config).unwrap()
+    }
+
+    async fn requests(server: &MockServer) -> usize {
+        server.received_requests().await.unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn should_retry_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+
+        let http = http(3);
+        let response = http.send(http.get(server.uri())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(requests(&server).await, 3);
+    }
+
+    #[tokio::test]
+    async fn should_give_up_after_retries() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET")).respond_with(ResponseTemplate::new(500)).mount(&server).await;
+
+        let http = http(2);
+        let response = http.send(http.get(server.uri())).await.unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(requests(&server).await, 3);
+    }
+
+    #[tokio::test]
+    async fn should_not_retry_client_errors() {
+        let server = MockServer::start().await;
+        Mock::given(path("/missing")).respond_with(ResponseTemplate::new(404)).mount(&server).await;
+
+        let http = http(3);
+        let response = http.send(http.get(format!("{}/missing", server.uri()))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(requests(&server).await, 1);
+    }
+
+    #[tokio::test]
+    async fn should_honour_retry_after() {
+        let server = MockServer::start().await;
+        Mock::given(path("/soon"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/soon")).respond_with(ResponseTemplate::new(200)).mount(&server).await;
+        Mock::given(path("/later"))
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "3600"))
+            .mount(&server)
+            .await;
+
+        let http = http(3);
+        let response = http.send(http.get(format!("{}/soon", server.uri()))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = http.send(http.get(format!("{}/later", server.uri()))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(requests(&server).await, 3);
+    }
+
+    #[tokio::test]
+    async fn should_retry_timeouts() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(500)))
+            .mount(&server)
+            .await;
+
+        let http = http(1);
+        let err = http.send(http.get(server.uri())).await.unwrap_err();
+        assert!(err.is_timeout());
+        assert_eq!(requests(&server).await, 2);
+    }
+
+    #[test]
+    fn should_parse_retry_after() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:27:00Z").unwrap().with_timezone(&Utc);
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers, now), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(120)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(retry_after(&headers, now), Some(Duration::from_secs(60)));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after(&headers, now), None);
+    }
+
+    #[test]
+    fn should_grow_backoff_within_bounds() {
+        let http = Http { backoff: Duration::from_millis(100), ..http(5) };
+        for (attempt, cap) in [(0, 100), (1, 200), (2, 400), (4, 1000), (30, 1000)] {
+            let got = http.backoff(attempt);
+            let cap = Duration::from_millis(cap);
+            assert!(cap / 2 <= got && got <= cap, "{attempt}: {got:?}");
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! mod `icon` picks the notification icon for a weather condition.
+//!
+//! `OpenWeatherMap` condition ids, shared by every provider, map to the freedesktop weather icon
+//! names found in most icon themes, e.g. `weather-storm` for a thunderstorm. Clear and partly
+//! cloudy skies get the night variant when the icon code ends with `n`, e.g. `01n`.
+//!
+//! With `notification.icon_dir` set, an `<name>.svg` or `<name>.png` in that directory is used
+//! instead of the system theme, e.g. `~/.local/share/mausam/icons/weather-storm.svg`.
+
+use std::path::Path;
+
+use crate::models::WeatherEntity;
+
+/// Icon of notifications without a single condition, e.g. a combined one for several locations.
+pub const DEFAULT_ICON: &str = "weather-few-clouds";
+
+/// File extensions looked up in the custom icon directory, in order.
+const EXTENSIONS: [&str; 2] = ["svg", "png"];
+
+/// Freedesktop icon name for `weather`, e.g. `weather-clear-night` for `800` with icon `01n`.
+pub fn icon_name(weather: &WeatherEntity) -> &'static str {
+    let night = weather.icon.trim().ends_with('n');
+    match weather.id {
+        200..=299 => "weather-storm",
+        300..=399 | 500 | 520 => "weather-showers-scattered",
+        500..=599 => "weather-showers",
+        600..=699 => "weather-snow",
+        771 | 781 => "weather-severe-alert",
+        700..=799 => "weather-fog",
+        800 if night => "weather-clear-night",
+        800 => "weather-clear",
+        801 | 802 if night => "weather-few-clouds-night",
+        801 | 802 => "weather-few-clouds",
+        803 | 804 => "weather-overcast",
+        _ => DEFAULT_ICON,
+    }
+}
+
+/// The icon to show for `name`: its file in `dir` when there is one, otherwise the name, looked
+/// up in the system icon theme.
+pub fn resolve(name: &str, dir: Option<&Path>) -> String {
+    dir.into_iter()
+        .flat_map(|dir| EXTENSIONS.map(|ext| dir.join(format!("{name}.{ext}"))))
+        .find(|path| path.is_file())
+        .map_or_else(|| name.to_string(), |path| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn weather(id: i32, icon: &str) -> WeatherEntity {
+        WeatherEntity {
+            id,
+            main: String::new(),
+            description: String::new(),
+            icon: icon.to_string(),
+        }
+    }
+
+    #[test]
+    fn should_map_conditions_to_icon_names() {
+        let cases = [
+            (200, "11d", "weather-storm"),
+            (232, "11n", "weather-storm"),
+            (301, "09d", "weather-showers-scattered"),
+            (500, "10d", "weather-showers-scattered"),
+            (502, "10n", "weather-showers"),
+            (511, "13d", "weather-showers"),
+            (520, "09d", "weather-showers-scattered"),
+            (522, "09d", "weather-showers"),
+            (601, "13n", "weather-snow"),
+            (741, "50d", "weather-fog"),
+            (781, "50d", "weather-severe-alert"),
+            (800, "01d", "weather-clear"),
+            (800, "01n", "weather-clear-night"),
+            (801, "02d", "weather-few-clouds"),
+            (802, "03n", "weather-few-clouds-night"),
+            (803, " 04n", "weather-overcast"),
+            (804, "04d", "weather-overcast"),
+            (900, "", DEFAULT_ICON),
+        ];
+        for (id, icon, expect) in cases {
+            assert_eq!(icon_name(&weather(id, icon)), expect, "{id} {icon}");
+        }
+    }
+
+    #[test]
+    fn should_prefer_icon_from_custom_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("weather-storm.png"), "").unwrap();
+
+        let got = resolve("weather-storm", Some(dir.path()));
+        assert_eq!(got, dir.path().join("weather-storm.png").display().to_string());
+        assert_eq!(resolve("weather-snow", Some(dir.path())), "weather-snow");
+        assert_eq!(resolve("weather-storm", None), "weather-storm");
+    }
+}
@@ -0,0 +1,195 @@
+//! mod `locate` lists the places matching an ambiguous place name, and lets the user choose
+//! and save one of them.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+
+use super::{provider::WeatherProvider, Report};
+use crate::{
+    config::{Config, SavedLocation},
+    models::Place,
+};
+
+/// Maximum number of candidates asked from the geocoding API.
+const CANDIDATES: usize = 5;
+
+/// Look up the places matching `query`, in `country` when set, and choose one with `pick` or
+/// `country`.
+///
+/// Without a choice, all candidates are reported. The chosen place is saved as `save` in the
+/// config file at `config_path`.
+///
+/// # Errors
+///
+/// Returns an error if the lookup fails, nothing matches, the choice is out of range, or a
+/// place should be saved without being chosen.
+pub async fn locate(
+    provider: &dyn WeatherProvider,
+    query: &str,
+    pick: Option<u32>,
+    country: Option<&str>,
+    save: Option<&str>,
+    config_path: Option<PathBuf>,
+) -> anyhow::Result<Report> {
+    let candidates = provider
+        .geocode(query, country, CANDIDATES)
+        .await
+        .with_context(|| format!("Failed to look up `{query}` with `{}`", provider.name()))?;
+
+    let Some(place) = choose(&candidates, pick, country)? else {
+        if save.is_some() {
+            bail!(
+                "{} places match `{query}`, choose one with `--pick` or `--country`",
+                candidates.len()
+            );
+        }
+        return Ok(Report::Places(candidates));
+    };
+
+    match save {
+        Some(name) => {
+            let path = config_path.context("Failed to find the config directory")?;
+            let location = SavedLocation {
+                lat: Some(place.lat),
+                lon: Some(place.lon),
+                label: Some(place.label()),
+                ..SavedLocation::default()
+            };
+            Config::save_location(&path, name, &location)?;
+
+            Ok(Report::Saved { name: name.to_string(), path, place: place.clone() })
+        }
+        None => Ok(Report::Places(vec![place.clone()])),
+    }
+}
+
+/// Choose among `candidates`: the `pick`th of those in `country`, or the first one in
+/// `country`. A single candidate is chosen without asking.
+///
+/// Returns `None` when the choice is left to the user.
+fn choose<'a>(
+    candidates: &'a [Place],
+    pick: Option<u32>,
+    country: Option<&str>,
+) -> anyhow::Result<Option<&'a Place>> {
+    let matches: Vec<&Place> = candidates
+        .iter()
+        .filter(|place| {
+            country.is_none_or(|code| {
+                place.country.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(code))
+            })
+        })
+        .collect();
+
+    match (matches.as_slice(), pick) {
+        ([], _) => match country {
+            Some(code) => bail!("No place found in `{code}`"),
+            None => bail!("No place found"),
+        },
+        (matches, Some(n)) => match matches.get(n as usize - 1) {
+            Some(place) => Ok(Some(place)),
+            None => bail!("Cannot pick candidate {n}, there are only {}", matches.len()),
+        },
+        ([place], None) => Ok(Some(place)),
+        ([place, ..], None) if country.is_some() => Ok(Some(place)),
+        (_, None) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        app::provider::Query,
+        models::{Forecast, Observation},
+    };
+
+    /// Geocoder that ranks many French places first and filters by country like the APIs do.
+    struct Geocoder(Vec<Place>);
+
+    #[async_trait]
+    impl WeatherProvider for Geocoder {
+        fn name(&self) -> &'static str {
+            "geocoder"
+        }
+
+        async fn current(&self, _: &Query) -> anyhow::Result<Observation> {
+            bail!("Only geocodes")
+        }
+
+        async fn forecast(&self, _: &Query) -> anyhow::Result<Forecast> {
+            bail!("Only geocodes")
+        }
+
+        async fn geocode(
+            &self,
+            _: &str,
+            country: Option<&str>,
+            limit: usize,
+        ) -> anyhow::Result<Vec<Place>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|place| country.is_none_or(|code| place.country.as_deref() == Some(code)))
+                .take(limit)
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn place(state: &str, country: &str) -> Place {
+        Place {
+            name: "Paris".to_string(),
+            state: Some(state.to_string()),
+            country: Some(country.to_string()),
+            lat: 0.0,
+            lon: 0.0,
+        }
+    }
+
+    fn candidates() -> Vec<Place> {
+        vec![place("Ile-de-France", "FR"), place("Texas", "US"), place("Tennessee", "US")]
+    }
+
+    #[test]
+    fn should_leave_the_choice_to_the_user() {
+        assert_eq!(choose(&candidates(), None, None).unwrap(), None);
+    }
+
+    #[test]
+    fn should_pick_by_index_within_country() {
+        let candidates = candidates();
+        assert_eq!(choose(&candidates, Some(2), None).unwrap(), Some(&candidates[1]));
+        assert_eq!(choose(&candidates, Some(2), Some("us")).unwrap(), Some(&candidates[2]));
+        assert!(choose(&candidates, Some(4), None).is_err());
+    }
+
+    #[test]
+    fn should_choose_first_in_country() {
+        let candidates = candidates();
+        assert_eq!(choose(&candidates, None, Some("US")).unwrap(), Some(&candidates[1]));
+        assert!(choose(&candidates, None, Some("DE")).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_look_up_places_in_the_country() {
+        let mut places = vec![place("Ile-de-France", "FR"); CANDIDATES];
+        places.extend(candidates().into_iter().skip(1));
+        let geocoder = Geocoder(places);
+
+        let report = locate(&geocoder, "paris", Some(2), Some("US"), None, None).await.unwrap();
+        let Report::Places(got) = report else { panic!("Expected places, got {report:?}") };
+        assert_eq!(got, [place("Tennessee", "US")]);
+    }
+
+    #[test]
+    fn should_choose_single_candidate() {
+        let candidates = &candidates()[..1];
+        assert_eq!(choose(candidates, None, None).unwrap(), Some(&candidates[0]));
+        assert!(choose(&[], None, None).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! mod `location` decides where to report the weather for.
+//!
+//! A [`LocationResolver`] tries an ordered list of [`Strategy`]s and stops at the first one
+//! that produces an answer:
+//!
+//! 1. `cli`: the place or `--lat`/`--lon` coordinates passed on the command line.
+//! 2. `home`: the `place` set in the config file or `MAUSAM_PLACE`.
+//! 3. `cache`: the machine's location from an earlier run, while younger than
+//!    `cache.location_ttl` seconds.
+//! 4. `geoip`: the machine's location from a local `GeoLite2` database, see `location.geoip_db`.
+//! 5. `ipapi`: the machine's location from ip-api.com.
+//!
+//! Strategies that are not configured are skipped, so a given place never touches the network.
+//! Neither does `--offline`, which skips `ipapi`.
+//! A located machine is queried by its coordinates, a city name is often ambiguous.
+//! The list can be narrowed with `--locate-with` or `location.strategies`.
+
+mod geoip;
+mod ipapi;
+
+use std::fmt;
+
+use anyhow::bail;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{http::Http, provider::Query};
+use crate::{
+    cache,
+    cli::Cli,
+    config::{Config, SavedLocation},
+    error::MausamError,
+};
+
+/// Name of the location cache file, inside the cache directory.
+const CACHE_FILE: &str = "location.json";
+
+/// A way of finding out where to report the weather for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// The place passed on the command line.
+    Cli,
+    /// The place from the config file or `MAUSAM_PLACE`.
+    Home,
+    /// The location cached by an earlier lookup.
+    Cache,
+    /// A lookup in the local `GeoLite2` database.
+    Geoip,
+    /// A lookup with ip-api.com.
+    Ipapi,
+}
+
+impl Strategy {
+    /// Every strategy, in the default order.
+    pub const ALL: [Strategy; 5] =
+        [Strategy::Cli, Strategy::Home, Strategy::Cache, Strategy::Geoip, Strategy::Ipapi];
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cli => write!(f, "cli"),
+            Self::Home => write!(f, "home"),
+            Self::Cache => write!(f, "cache"),
+            Self::Geoip => write!(f, "geoip"),
+            Self::Ipapi => write!(f, "ipapi"),
+        }
+    }
+}
+
+/// A resolved location of the machine.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Location {
+    pub city: String,
+    pub country: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl From<ipapi::IpApiResponse> for Location {
+    fn from(response: ipapi::IpApiResponse) -> Self {
+        Self {
+            city: response.city,
+            country: Some(response.country_code),
+            lat: response.lat,
+            lon: response.lon,
+        }
+    }
+}
+
+impl From<Location> for Query {
+    fn from(location: Location) -> Self {
+        Query::Coords { lat: location.lat, lon: location.lon, name: Some(location.city) }
+    }
+}
+
+/// The named locations selected with `--location` or `--all`, in the order given.
+///
+/// Returns an empty list when neither flag is passed.
+///
+/// # Errors
+///
+/// Returns an error if a name is not in the config file, or `--all` finds no locations.
+pub fn named_locations<'a>(
+    config: &'a Config,
+    cli: &Cli,
+) -> anyhow::Result<Vec<(&'a str, &'a SavedLocation)>> {
+    if cli.all {
+        if config.locations.is_empty() {
+            bail!("`--all` needs `[locations.<name>]` entries in the config file");
+        }
+        return Ok(config
+            .locations
+            .iter()
+            .map(|(name, location)| (name.as_str(), location))
+            .collect());
+    }
+
+    let names = cli.location.as_deref().unwrap_or_default();
+    names
+        .iter()
+        .map(|name| match config.locations.get_key_value(name) {
+            Some((name, location)) => Ok((name.as_str(), location)),
+            None => bail!(
+                "No location `{name}` in the config file, known locations: {:?}",
+                config.locations.keys().collect::<Vec<_>>()
+            ),
+        })
+        .collect()
+}
+
+/// Tries the configured strategies in order until one resolves a [`Query`].
+#[derive(Debug)]
+pub struct LocationResolver<'a> {
+    config: &'a Config,
+    cli: &'a Cli,
+    http: &'a Http,
+    strategies: Vec<Strategy>,
+    refresh: bool,
+}
+
+impl<'a> LocationResolver<'a> {
+    /// Build the resolver from the config and the command line.
+    ///
+    /// Strategies come from `--locate-with`, then `location.strategies`, then the default
+    /// order. `--refresh-location` drops the `cache` strategy.
+    pub fn new(config: &'a Config, cli: &'a Cli, http: &'a Http) -> Self {
+        let strategies = cli
+            .locate_with
+            .clone()
+            .or_else(|| config.location.strategies.clone())
+            .unwrap_or_else(|| Strategy::ALL.to_vec());
+
+        Self { config, cli, http, strategies, refresh: cli.refresh_location }
+    }
+
+    /// Try each strategy in order and return the first answer.
+    ///
+    /// Lookups by `geoip` and `ipapi` are cached for the `cache` strategy of later runs. A
+    /// cache that cannot be read or written only logs a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the failures if no strategy produced a query.
+    pub async fn resolve(&self) -> anyhow::Result<Query> {
+        let now = chrono::Utc::now().timestamp();
+        let mut failures = Vec::new();
+
+        for &strategy in &self.strategies {
+            match self.try_strategy(strategy, now).await {
+                Ok(Some(query)) => {
+                    log::info!("Located `{query}` with the `{strategy}` strategy");
+                    return Ok(query);
+                }
+                Ok(None) => log::debug!("Skipped the `{strategy}` strategy"),
+                Err(err) => {
+                    log::warn!("The `{strategy}` strategy failed: {err:#}");
+                    failures.push(format!("{strategy}: {err:#}"));
+                }
+            }
+        }
+
+        if failures.is_empty() {
+            bail!("No location strategy applies, tried: {:?}", self.strategies)
+        } else {
+            bail!("Failed to find the location. {}", failures.join("; "))
+        }
+    }
+
+    /// Run one strategy. `Ok(None)` means the strategy does not apply.
+    async fn try_strategy(&self, strategy: Strategy, now: i64) -> anyhow::Result<Option<Query>> {
+        let location = &self.config.location;
+        match strategy {
+            Strategy::Cli => match (self.cli.lat, self.cli.lon) {
+                (Some(lat), Some(lon)) => Ok(Some(Query::coords(lat, lon)?)),
+                _ => self.cli.place().map(str::parse).transpose(),
+            },
+            Strategy::Home => self.config.place.as_deref().map(str::parse).transpose(),
+            Strategy::Cache => {
+                let Some(path) = self.cache_path().filter(|_| !self.refresh) else {
+                    return Ok(None);
+                };
+                Ok(cache::read::<Location>(&path)?
+                    .filter(|cached| cached.is_fresh(now, self.config.cache.location_ttl))
+                    .map(|cached| Query::from(cached.value)))
+            }
+            Strategy::Geoip => match &location.geoip_db {
+                Some(db) => Ok(Some(self.save(geoip::lookup(db, location.ip)?, now))),
+                None => Ok(None),
+            },
+            Strategy::Ipapi => {
+                // With a local database configured, only go online when explicitly allowed.
+                let allowed = location.geoip_db.is_none()
+                    || location.ipapi_fallback
+                    || !self.strategies.contains(&Strategy::Geoip);
+                if !allowed || self.config.cache.offline {
+                    return Ok(None);
+                }
+                let response =
+                    ipapi::get_ip_api_location(self.http).await.map_err(MausamError::from)?;
+                Ok(Some(self.save(Location::from(response), now)))
+            }
+        }
+    }
+
+    fn cache_path(&self) -> Option<std::path::PathBuf> {
+        cache::dir(&self.config.cache).map(|dir| dir.join(CACHE_FILE))
+    }
+
+    /// Cache a freshly looked up `location` for later runs and turn it into a query.
+    fn save(&self, location: Location, now: i64) -> Query {
+        if let Some(path) = self.cache_path() {
+            if let Err(err) = cache::write(&path, &location, now) {
+                log::warn!("Failed to cache location: {err:#}");
+            }
+        }
+        Query::from(location)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn http() -> Http {
+        Http::new(&crate::config::HttpConfig::default()).unwrap()
+    }
+
+    fn located(city: &str) -> Location {
+        Location { city: city.to_string(), country: None, lat: 1.0, lon: 2.0 }
+    }
+
+    /// A config with a home place and a fresh cached location.
+    fn config(dir: &tempfile::TempDir) -> Config {
+        let mut config = Config { place: Some("Home".to_string()), ..Config::default() };
+        config.cache.dir = Some(dir.path().to_path_buf());
+        let now = chrono::Utc::now().timestamp();
+        cache::write(&dir.path().join(CACHE_FILE), &located("Cached"), now).unwrap();
+        config
+    }
+
+    #[test]
+    fn should_select_named_locations() {
+        let mut config = Config::default();
+        for name in ["office", "home"] {
+            let location =
+                SavedLocation { place: Some(name.to_string()), ..SavedLocation::default() };
+            config.locations.insert(name.to_string(), location);
+        }
+        let names = |cli: &[&str]| -> anyhow::Result<Vec<String>> {
+            let cli = Cli::parse_from(cli);
+            Ok(named_locations(&config, &cli)?
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect())
+        };
+
+        assert_eq!(names(&["mausam"]).unwrap(), Vec::<String>::new());
+        assert_eq!(names(&["mausam", "--location", "office,home"]).unwrap(), ["office", "home"]);
+        assert_eq!(names(&["mausam", "--all"]).unwrap(), ["home", "office"]);
+        assert!(names(&["mausam", "--location", "gym"]).is_err());
+    }
+
+    #[tokio::test]
+    async fn should_prefer_cli_over_home() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(&dir);
+        let cli = Cli::parse_from(["mausam", "paris"]);
+
+        let got = LocationResolver::new(&config, &cli, &http()).resolve().await.unwrap();
+        assert_eq!(got, Query::Place("paris".to_string()));
+
+        let cli = Cli::parse_from(["mausam", "--lat", "-33.92", "--lon", "18.42"]);
+        let got = LocationResolver::new(&config, &cli, &http()).resolve().await.unwrap();
+        assert_eq!(got, Query::Coords { lat: -33.92, lon: 18.42, name: None });
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_home_then_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config(&dir);
+        let cli = Cli::parse_from(["mausam"]);
+
+        let got = LocationResolver::new(&config, &cli, &http()).resolve().await.unwrap();
+        assert_eq!(got, Query::Place("Home".to_string()));
+
+        config.place = None;
+        let got = LocationResolver::new(&config, &cli, &http()).resolve().await.unwrap();
+        assert_eq!(got, Query::from(located("Cached")));
+    }
+
+    #[tokio::test]
+    async fn should_only_use_selected_strategies() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(&dir);
+        let cli = Cli::parse_from(["mausam", "paris", "--locate-with", "cache"]);
+
+        let got = LocationResolver::new(&config, &cli, &http()).resolve().await.unwrap();
+        assert_eq!(got, Query::from(located("Cached")));
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_no_strategy_applies() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config(&dir);
+        let cli = Cli::parse_from(["mausam", "--locate-with", "cache", "--refresh-location"]);
+
+        assert!(LocationResolver::new(&config, &cli, &http()).resolve().await.is_err());
+    }
+}
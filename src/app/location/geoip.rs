@@ -0,0 +1,161 @@
+//! Offline IP geolocation with a local `MaxMind` `GeoLite2` City database.
+//!
+//! Nothing leaves the machine: the database file is read locally, and the machine's own
+//! address is found by asking the OS which source address it would route a packet from.
+//! Get the database from [MaxMind](https://dev.maxmind.com/geoip/geolite2-free-geolocation-data).
+
+use std::{
+    net::{IpAddr, UdpSocket},
+    path::Path,
+};
+
+use anyhow::{anyhow, bail, Context};
+use maxminddb::geoip2;
+
+use super::Location;
+
+/// Any public address works here, connecting a UDP socket does not send packets.
+const ROUTE_PROBE_ADDR: &str = "1.1.1.1:53";
+
+/// Look up `ip`, or the machine's own public address, in the database at `db`.
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be read, the machine has no public address and
+/// `ip` is not given, or the database has no city and coordinates for the address.
+pub fn lookup(db: &Path, ip: Option<IpAddr>) -> anyhow::Result<Location> {
+    let ip = match ip {
+        Some(ip) => ip,
+        None => local_public_ip()?,
+    };
+    let reader = maxminddb::Reader::open_readfile(db).map_err(|e| {
+        anyhow!(e).context(format!("Failed to open GeoIP database `{}`", db.display()))
+    })?;
+    let city: geoip2::City<'_> = reader.lookup(ip).map_err(|e| {
+        anyhow!(e).context(format!("Failed to look up `{ip}` in `{}`", db.display()))
+    })?;
+
+    to_location(&city).with_context(|| format!("Incomplete GeoIP record for `{ip}`"))
+}
+
+/// The address the machine uses to reach the internet, if it is publicly routable.
+fn local_public_ip() -> anyhow::Result<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(ROUTE_PROBE_ADDR).context("Failed to find the machine's IP address")?;
+    let ip = socket.local_addr()?.ip();
+
+    if !is_global(ip) {
+        bail!(
+            "The machine's address `{ip}` is not public. Set `location.ip` to the public IP, \
+             or enable `location.ipapi_fallback` in the config file"
+        );
+    }
+    Ok(ip)
+}
+
+/// Whether `ip` is routable on the public internet.
+fn is_global(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                // Shared address space for carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                // Unique local, fc00::/7, and link local, fe80::/10.
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Map a `GeoLite2` City record to a `Location`, preferring English names.
+fn to_location(record: &geoip2::City<'_>) -> Option<Location> {
+    let english = |names: Option<&std::collections::BTreeMap<&str, &str>>| {
+        names.and_then(|names| names.get("en")).map(ToString::to_string)
+    };
+    let city =
+        record.city.as_ref().and_then(|city| english(city.names.as_ref())).or_else(|| {
+            let subdivision = record.subdivisions.as_ref()?.first()?;
+            english(subdivision.names.as_ref())
+        })?;
+    let location = record.location.as_ref()?;
+
+    Some(Location {
+        city,
+        country: record.country.as_ref().and_then(|c| c.iso_code).map(ToString::to_string),
+        lat: location.latitude?,
+        lon: location.longitude?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_detect_global_addresses() {
+        for ip in ["8.8.8.8", "81.2.69.142", "2a02:ec80::1"] {
+            assert!(is_global(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["10.0.0.1", "192.168.1.20", "127.0.0.1", "100.64.1.1", "fd00::1", "fe80::1"] {
+            assert!(!is_global(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn should_map_city_record() {
+        let record = geoip2::City {
+            city: Some(geoip2::city::City {
+                geoname_id: Some(2643743),
+                names: Some(BTreeMap::from([("de", "London"), ("en", "London")])),
+            }),
+            continent: None,
+            country: Some(geoip2::city::Country {
+                geoname_id: None,
+                is_in_european_union: None,
+                iso_code: Some("GB"),
+                names: None,
+            }),
+            location: Some(geoip2::city::Location {
+                accuracy_radius: Some(100),
+                latitude: Some(51.5142),
+                longitude: Some(-0.0931),
+                metro_code: None,
+                time_zone: Some("Europe/London"),
+            }),
+            postal: None,
+            registered_country: None,
+            represented_country: None,
+            subdivisions: None,
+            traits: None,
+        };
+
+        let got = to_location(&record).unwrap();
+        assert_eq!(
+            got,
+            Location {
+                city: "London".to_string(),
+                country: Some("GB".to_string()),
+                lat: 51.5142,
+                lon: -0.0931,
+            }
+        );
+
+        let without_coords = geoip2::City { location: None, ..record };
+        assert_eq!(to_location(&without_coords), None);
+    }
+}
@@ -0,0 +1,65 @@
+//! Online IP geolocation with the free [ip-api.com](https://ip-api.com/docs/api:json) API.
+
+use reqwest::{self, Response};
+use serde::{Deserialize, Serialize};
+
+use crate::app::{http::Http, IP_API_URL};
+
+pub(crate) type ResultIpApi = anyhow::Result<IpApiResponse, reqwest::Error>;
+
+#[derive(Deserialize, Debug, Serialize)]
+pub(crate) struct IpApiResponse {
+    pub(crate) status: String,
+    pub(crate) country: String,
+    #[serde(rename = "countryCode")]
+    pub(crate) country_code: String,
+    pub(crate) region: String,
+    #[serde(rename = "regionName")]
+    pub(crate) region_name: String,
+    pub(crate) city: String,
+    pub(crate) zip: String,
+    pub(crate) lat: f64,
+    pub(crate) lon: f64,
+    pub(crate) timezone: String,
+    pub(crate) isp: String, // The ISP name for the location.
+    pub(crate) org: String, // The organization name for the location.
+    #[serde(rename = "as")]
+    pub(crate) as_: String,
+}
+
+/// `get_ip_api_location` fetches the current ip location.
+///
+/// * Use the shared `http` client to make the request to the `IP_API_URL`.
+/// * Extract the JSON from the response and parse it into an `IpApiResponse`.
+/// * Return the parsed JSON as the result of the function.
+pub(crate) async fn get_ip_api_location(http: &Http) -> ResultIpApi {
+    let response: Response = http.send(http.get(*IP_API_URL)).await?;
+    response.json::<IpApiResponse>().await
+}
+
+#[test]
+fn should_rename_fields() {
+    let api_response = IpApiResponse {
+        status: "success".to_string(),
+        country: "United States".to_string(),
+        country_code: "US".to_string(),
+        region: "CA".to_string(),
+        region_name: "California".to_string(),
+        city: "San Francisco".to_string(),
+        zip: "94107".to_string(),
+        lat: 37.7749,
+        lon: -122.4194,
+        timezone: "America/Los_Angeles".to_string(),
+        isp: "Google".to_string(),
+        org: "Google LLC".to_string(),
+        as_: "".to_string(),
+    };
+    assert_eq!(
+        serde_json::to_string(&api_response).unwrap(),
+        "{\"status\":\"success\",\"country\":\"United \
+         States\",\"countryCode\":\"US\",\"region\":\"CA\",\"regionName\":\"California\",\"\
+         city\":\"San \
+         Francisco\",\"zip\":\"94107\",\"lat\":37.7749,\"lon\":-122.4194,\"timezone\":\"\
+         America/Los_Angeles\",\"isp\":\"Google\",\"org\":\"Google LLC\",\"as\":\"\"}"
+    );
+}
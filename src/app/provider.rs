@@ -0,0 +1,219 @@
+//! mod `provider` abstracts over the weather APIs `mausam` can fetch observations from.
+//!
+//! Every backend implements [`WeatherProvider`] and maps its own response schema into the
+//! provider-neutral [`Observation`]. The rest of the app only ever sees an `Observation`, so
+//! switching providers does not touch the notification code.
+
+mod cached;
+mod open_meteo;
+mod openweathermap;
+
+use std::{fmt, str::FromStr, sync::Arc};
+
+use anyhow::bail;
+use async_trait::async_trait;
+use clap::ValueEnum;
+use reqwest::{Response, StatusCode};
+use serde::{Deserialize, Serialize};
+
+pub use self::{cached::Cached, open_meteo::OpenMeteo, openweathermap::OpenWeatherMap};
+use super::http::Http;
+use crate::{
+    cache,
+    config::{Config, API_KEY_VAR},
+    error::MausamError,
+    models::{Forecast, Observation, Place},
+};
+
+/// The weather providers `mausam` knows how to talk to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderKind {
+    /// `OpenWeatherMap`, needs `WEATHER_API_KEY`.
+    Openweathermap,
+    /// Open-Meteo, keyless.
+    OpenMeteo,
+}
+
+/// What to ask a provider the weather for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// A place name, geocoded by the provider.
+    Place(String),
+    /// Coordinates in decimal degrees, with an optional name to display.
+    Coords { lat: f64, lon: f64, name: Option<String> },
+}
+
+impl Query {
+    /// Build a coordinate query, checking that `lat` and `lon` are in range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the latitude is outside `-90..=90` or the longitude outside
+    /// `-180..=180`.
+    pub fn coords(lat: f64, lon: f64) -> anyhow::Result<Self> {
+        if !(-90.0..=90.0).contains(&lat) {
+            bail!("Latitude `{lat}` is not between -90 and 90");
+        }
+        if !(-180.0..=180.0).contains(&lon) {
+            bail!("Longitude `{lon}` is not between -180 and 180");
+        }
+        Ok(Self::Coords { lat, lon, name: None })
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Place(place) => write!(f, "{place}"),
+            Self::Coords { name: Some(name), .. } => write!(f, "{name}"),
+            Self::Coords { lat, lon, name: None } => write!(f, "{lat},{lon}"),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = anyhow::Error;
+
+    /// Parse `lat,lon` as coordinates and anything else as a place name.
+    ///
+    /// Out of range coordinates are an error rather than a place name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let coords = s.split_once(',').and_then(|(lat, lon)| {
+            Some((lat.trim().parse::<f64>().ok()?, lon.trim().parse::<f64>().ok()?))
+        });
+        match coords {
+            Some((lat, lon)) => Self::coords(lat, lon),
+            None => Ok(Self::Place(s.to_string())),
+        }
+    }
+}
+
+/// A source of current weather observations.
+#[async_trait]
+pub trait WeatherProvider: Send + Sync {
+    /// Short, stable identifier of the provider, e.g. `openweathermap`.
+    fn name(&self) -> &'static str;
+
+    /// Fetch the current weather at `query`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response cannot be mapped into an
+    /// `Observation`.
+    async fn current(&self, query: &Query) -> anyhow::Result<Observation>;
+
+    /// Fetch the forecast for the next few days at `query`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response cannot be mapped into a
+    /// `Forecast`.
+    async fn forecast(&self, query: &Query) -> anyhow::Result<Forecast>;
+
+    /// Look up to `limit` places matching the place name `query`, only in `country` when set,
+    /// by ISO 3166 code.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or the response cannot be deserialized.
+    async fn geocode(
+        &self,
+        query: &str,
+        country: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Place>>;
+}
+
+/// Build the provider configured in `config`, sending its requests with `http`.
+///
+/// Without an explicit provider, `OpenWeatherMap` is used when an API key is configured and the
+/// keyless Open-Meteo otherwise, so a fresh install works out of the box. Responses are cached
+/// for `cache.weather_ttl` seconds.
+///
+/// # Errors
+///
+/// Returns an error if `OpenWeatherMap` is requested but no API key is configured.
+pub fn new_provider(config: &Config, http: &Http) -> anyhow::Result<Arc<dyn WeatherProvider>> {
+    let kind = config.provider.unwrap_or_else(|| {
+        if config.api_key.is_some() {
+            ProviderKind::Openweathermap
+        } else {
+            log::info!("No API key configured, falling back to the keyless Open-Meteo provider");
+            ProviderKind::OpenMeteo
+        }
+    });
+
+    let provider: Arc<dyn WeatherProvider> = match kind {
+        ProviderKind::Openweathermap => {
+            let Some(api_key) = config.api_key.as_deref() else {
+                return Err(anyhow::Error::new(MausamError::MissingApiKey).context(format!(
+                    "`{API_KEY_VAR}` is not set and the config file has no `api_key`"
+                )));
+            };
+            Arc::new(OpenWeatherMap::new(api_key, config.lang.clone(), http.clone()))
+        }
+        ProviderKind::OpenMeteo => Arc::new(OpenMeteo::new(http.clone())),
+    };
+
+    Ok(match cache::dir(&config.cache) {
+        Some(dir) => Arc::new(Cached::new(provider, dir, config)),
+        None if config.cache.offline => bail!("`--offline` needs a cache directory"),
+        None => provider,
+    })
+}
+
+/// Turn an error status of `response` to a request for `query` into a [`MausamError`].
+///
+/// # Errors
+///
+/// Returns an error for any 4xx or 5xx status.
+pub(crate) fn check_status(response: Response, query: &Query) -> Result<Response, MausamError> {
+    match status_error(response.status(), query) {
+        Some(err) => Err(err),
+        None => Ok(response.error_for_status()?),
+    }
+}
+
+/// The [`MausamError`] for the statuses with a known cause.
+pub(crate) fn status_error(status: StatusCode, query: &Query) -> Option<MausamError> {
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Some(MausamError::InvalidApiKey),
+        StatusCode::NOT_FOUND => Some(MausamError::PlaceNotFound(query.to_string())),
+        StatusCode::TOO_MANY_REQUESTS => Some(MausamError::RateLimited),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_coordinates_or_place() {
+        let got: Query = "48.8534, 2.3488".parse().unwrap();
+        assert_eq!(got, Query::Coords { lat: 48.8534, lon: 2.3488, name: None });
+
+        let got: Query = "-33.92,18.42".parse().unwrap();
+        assert_eq!(got, Query::Coords { lat: -33.92, lon: 18.42, name: None });
+
+        let got: Query = "Paris, FR".parse().unwrap();
+        assert_eq!(got, Query::Place("Paris, FR".to_string()));
+    }
+
+    #[test]
+    fn should_reject_out_of_range_coordinates() {
+        assert!("91,0".parse::<Query>().is_err());
+        assert!(Query::coords(0.0, -180.5).is_err());
+    }
+
+    #[test]
+    fn should_display_name_over_coordinates() {
+        let query = Query::coords(48.85, 2.35).unwrap();
+        assert_eq!(query.to_string(), "48.85,2.35");
+        let query = Query::Coords { lat: 48.85, lon: 2.35, name: Some("Paris".to_string()) };
+        assert_eq!(query.to_string(), "Paris");
+    }
+}
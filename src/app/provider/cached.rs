@@ -0,0 +1,322 @@
+//! mod `cached` keeps recent provider responses on disk, so runs from cron, a status bar and
+//! the shell share one request per freshness window.
+
+use std::{future::Future, path::PathBuf, sync::Arc};
+
+use anyhow::bail;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{Query, WeatherProvider};
+use crate::{
+    cache,
+    config::{Config, Units},
+    error,
+    models::{Forecast, Observation, Place, Stale},
+};
+
+/// Subdirectory of the cache directory holding the responses.
+const WEATHER_DIR: &str = "weather";
+
+/// [`WeatherProvider`] that serves responses of `inner` younger than `max_age` seconds from
+/// the cache directory.
+///
+/// Fresh responses are always written back, so even with `max_age` zero the cache holds the
+/// last response for each query. That response is served, marked as stale, when `offline` is
+/// set or `inner` is unavailable. Place lookups are not cached.
+pub struct Cached {
+    inner: Arc<dyn WeatherProvider>,
+    dir: PathBuf,
+    max_age: u64,
+    offline: bool,
+    units: Units,
+    lang: Option<String>,
+}
+
+impl Cached {
+    pub fn new(inner: Arc<dyn WeatherProvider>, dir: PathBuf, config: &Config) -> Self {
+        Self {
+            inner,
+            dir: dir.join(WEATHER_DIR),
+            max_age: config.cache.weather_ttl,
+            offline: config.cache.offline,
+            units: config.units,
+            lang: config.lang.clone(),
+        }
+    }
+
+    /// Cache file of the `kind` of response at `query`, keyed by provider, query, units and
+    /// language.
+    fn path(&self, kind: &str, query: &Query) -> PathBuf {
+        let query = match query {
+            Query::Place(place) => place.to_lowercase(),
+            Query::Coords { lat, lon, .. } => format!("{lat:.4},{lon:.4}"),
+        };
+        let units = serde_json::to_value(self.units).ok();
+        let units = units.as_ref().and_then(|units| units.as_str()).unwrap_or_default();
+        let lang = self.lang.as_deref().unwrap_or("en");
+        let key: String = format!("{}-{kind}-{units}-{lang}-{query}", self.inner.name())
+            .chars()
+            .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '.' | ',') { c } else { '_' })
+            .collect();
+
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// The cached `kind` of response at `query` if fresh, else the result of `fetch`, which is
+    /// then cached. A cache that cannot be read or written only logs a warning.
+    ///
+    /// Offline, or when the provider is unavailable, the last cached response is returned
+    /// whatever its age, along with how stale it is.
+    async fn get_or_fetch<T, F>(
+        &self,
+        kind: &str,
+        query: &Query,
+        fetch: F,
+    ) -> anyhow::Result<(T, Option<Stale>)>
+    where
+        T: Serialize + DeserializeOwned,
+        F: Future<Output = anyhow::Result<T>>,
+    {
+        let path = self.path(kind, query);
+        let now = chrono::Utc::now().timestamp();
+
+        let cached = cache::read::<T>(&path).unwrap_or_else(|err| {
+            log::warn!("Ignoring the cached {kind}: {err:#}");
+            None
+        });
+        let cached = match cached {
+            Some(cached) if cached.is_fresh(now, self.max_age) => {
+                log::info!("Using the {kind} for `{query}` cached {}s ago", cached.age(now));
+                return Ok((cached.value, None));
+            }
+            Some(cached) if self.offline => {
+                log::info!(
+                    "Offline, using the {kind} for `{query}` cached {}s ago",
+                    cached.age(now)
+                );
+                return Ok((cached.value, Some(Stale::new(cached.saved_at, now))));
+            }
+            None if self.offline => bail!("Offline, and no {kind} for `{query}` in the cache"),
+            cached => cached,
+        };
+
+        let value = match fetch.await {
+            Ok(value) => value,
+            Err(err) if error::is_unavailable(&err) => {
+                let Some(cached) = cached else {
+                    return Err(err);
+                };
+                log::warn!(
+                    "{err:#}, using the {kind} for `{query}` cached {}s ago",
+                    cached.age(now)
+                );
+                return Ok((cached.value, Some(Stale::new(cached.saved_at, now))));
+            }
+            Err(err) => return Err(err),
+        };
+        if let Err(err) = cache::write(&path, &value, now) {
+            log::warn!("Failed to cache the {kind}: {err:#}");
+        }
+        Ok((value, None))
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for Cached {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    async fn current(&self, query: &Query) -> anyhow::Result<Observation> {
+        let (mut observation, stale) =
+            self.get_or_fetch("current", query, self.inner.current(query)).await?;
+        observation.stale = stale;
+        Ok(observation)
+    }
+
+    async fn forecast(&self, query: &Query) -> anyhow::Result<Forecast> {
+        let (mut forecast, stale) =
+            self.get_or_fetch("forecast", query, self.inner.forecast(query)).await?;
+        forecast.stale = stale;
+        Ok(forecast)
+    }
+
+    async fn geocode(
+        &self,
+        query: &str,
+        country: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Place>> {
+        self.inner.geocode(query, country, limit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{error::MausamError, models::OpenWeatherData};
+
+    /// Provider that counts its requests, and fails them with a network error while `down`, or
+    /// with too many requests while `rate_limited`.
+    #[derive(Default)]
+    struct Counting {
+        requests: AtomicUsize,
+        down: AtomicBool,
+        rate_limited: AtomicBool,
+    }
+
+    impl Counting {
+        fn request(&self) -> anyhow::Result<()> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            if self.rate_limited.load(Ordering::SeqCst) {
+                return Err(MausamError::RateLimited.into());
+            }
+            if self.down.load(Ordering::SeqCst) {
+                let err = reqwest::Client::new().get("no url").build().unwrap_err();
+                return Err(MausamError::Network(err).into());
+            }
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl WeatherProvider for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        async fn current(&self, _: &Query) -> anyhow::Result<Observation> {
+            self.request()?;
+            Observation::try_from(OpenWeatherData::default())
+        }
+
+        async fn forecast(&self, _: &Query) -> anyhow::Result<Forecast> {
+            self.request()?;
+            let data = OpenWeatherData::default();
+            Ok(Forecast {
+                name: data.name,
+                country: None,
+                coord: data.coord,
+                timezone: data.timezone,
+                slots: Vec::new(),
+                stale: None,
+            })
+        }
+
+        async fn geocode(&self, _: &str, _: Option<&str>, _: usize) -> anyhow::Result<Vec<Place>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn cached(
+        inner: &Arc<Counting>,
+        dir: &std::path::Path,
+        weather_ttl: u64,
+        offline: bool,
+    ) -> Cached {
+        let mut config = Config::default();
+        config.cache.weather_ttl = weather_ttl;
+        config.cache.offline = offline;
+        Cached::new(inner.clone(), dir.to_path_buf(), &config)
+    }
+
+    #[tokio::test]
+    async fn should_serve_fresh_responses_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = Arc::new(Counting::default());
+        let cached = cached(&inner, dir.path(), 600, false);
+        let paris = Query::Place("Paris".to_string());
+
+        let first = cached.current(&paris).await.unwrap();
+        let second = cached.current(&Query::Place("paris".to_string())).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.stale, None);
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 1);
+
+        cached.current(&Query::Place("London".to_string())).await.unwrap();
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn should_refetch_without_max_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = Arc::new(Counting::default());
+        let cached = cached(&inner, dir.path(), 0, false);
+        let paris = Query::Place("Paris".to_string());
+
+        cached.current(&paris).await.unwrap();
+        cached.current(&paris).await.unwrap();
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 2);
+        assert!(cached.path("current", &paris).exists());
+    }
+
+    #[tokio::test]
+    async fn should_serve_stale_response_offline() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = Arc::new(Counting::default());
+        let paris = Query::Place("Paris".to_string());
+
+        let offline = cached(&inner, dir.path(), 0, true);
+        assert!(offline.current(&paris).await.is_err());
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 0);
+
+        cached(&inner, dir.path(), 0, false).current(&paris).await.unwrap();
+        let got = offline.current(&paris).await.unwrap();
+        assert_eq!(got.stale.map(|stale| stale.as_of), Some("just now".to_string()));
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_stale_response_on_network_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = Arc::new(Counting::default());
+        let cached = cached(&inner, dir.path(), 0, false);
+        let paris = Query::Place("Paris".to_string());
+
+        inner.down.store(true, Ordering::SeqCst);
+        assert!(cached.current(&paris).await.is_err());
+
+        inner.down.store(false, Ordering::SeqCst);
+        cached.current(&paris).await.unwrap();
+        inner.down.store(true, Ordering::SeqCst);
+        let got = cached.current(&paris).await.unwrap();
+        assert!(got.stale.is_some());
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn should_fall_back_to_stale_forecast_when_rate_limited() {
+        let dir = tempfile::tempdir().unwrap();
+        let inner = Arc::new(Counting::default());
+        let cached = cached(&inner, dir.path(), 0, false);
+        let paris = Query::Place("Paris".to_string());
+
+        assert_eq!(cached.forecast(&paris).await.unwrap().stale, None);
+        inner.rate_limited.store(true, Ordering::SeqCst);
+        let got = cached.forecast(&paris).await.unwrap();
+        assert_eq!(got.stale.map(|stale| stale.as_of), Some("just now".to_string()));
+
+        let err = cached.forecast(&Query::Place("London".to_string())).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(MausamError::RateLimited)), "{err:?}");
+        assert_eq!(inner.requests.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn should_key_by_provider_query_and_units() {
+        let dir = PathBuf::from("/cache");
+        let config =
+            Config { units: Units::Imperial, lang: Some("de".to_string()), ..Config::default() };
+        let cached = Cached::new(Arc::new(Counting::default()), dir.clone(), &config);
+
+        let got = cached.path("current", &Query::Place("New York/NY".to_string()));
+        assert_eq!(got, dir.join("weather/counting-current-imperial-de-new_york_ny.json"));
+
+        let got = cached.path("forecast", &Query::coords(48.85341, 2.3488).unwrap());
+        assert_eq!(got, dir.join("weather/counting-forecast-imperial-de-48.8534,2.3488.json"));
+    }
+}
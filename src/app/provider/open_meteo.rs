@@ -0,0 +1,339 @@
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+
+use super::{check_status, Query, WeatherProvider};
+use crate::{
+    app::{http::Http, TempUnit, Temperature},
+    error::MausamError,
+    models::{
+        wmo_weather, Clouds, Coord, Forecast, ForecastSlot, Main, Observation, OpenMeteoData,
+        OpenMeteoForecast, OpenMeteoGeocoding, OpenMeteoPlace, Place, Wind,
+    },
+};
+
+/// Base URL of the Open-Meteo forecast API.
+pub const OPEN_METEO_API_URL: &str = "https://api.open-meteo.com";
+
+/// Base URL of the Open-Meteo geocoding API.
+pub const OPEN_METEO_GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com";
+
+/// Variables requested for the `current` block, mirroring the fields of `Observation`.
+const CURRENT_VARS: &str = "temperature_2m,relative_humidity_2m,apparent_temperature,is_day,\
+                            weather_code,cloud_cover,pressure_msl,wind_speed_10m,\
+                            wind_direction_10m,visibility";
+
+/// Variables requested for the `hourly` block of a forecast.
+const HOURLY_VARS: &str = "temperature_2m,relative_humidity_2m,apparent_temperature,\
+                           precipitation_probability,precipitation,is_day,weather_code,\
+                           cloud_cover,pressure_msl,wind_speed_10m,wind_direction_10m";
+
+/// Number of days requested for a forecast, matching the `OpenWeatherMap` 5 day forecast.
+const FORECAST_DAYS: &str = "5";
+
+/// Variables requested for the `daily` block.
+const DAILY_VARS: &str = "temperature_2m_max,temperature_2m_min,sunrise,sunset";
+
+/// [`WeatherProvider`] backed by the keyless Open-Meteo API.
+///
+/// Open-Meteo is queried by coordinates, so place names are resolved with its geocoding API
+/// first. Coordinate queries skip the geocoding.
+#[derive(Debug, Clone)]
+pub struct OpenMeteo {
+    http: Http,
+}
+
+impl OpenMeteo {
+    pub fn new(http: Http) -> Self {
+        Self { http }
+    }
+
+    /// Resolve `query` to a place, geocoding place names.
+    async fn place(&self, query: &Query) -> anyhow::Result<OpenMeteoPlace> {
+        match query {
+            Query::Place(place) => self.best_match(place).await,
+            #[allow(clippy::cast_possible_truncation)]
+            Query::Coords { lat, lon, name } => Ok(OpenMeteoPlace {
+                name: name.clone().unwrap_or_else(|| query.to_string()),
+                latitude: *lat as f32,
+                longitude: *lon as f32,
+                country_code: None,
+                country: None,
+                admin1: None,
+            }),
+        }
+    }
+
+    /// Resolve `query` to the best matching place with the Open-Meteo geocoding API.
+    async fn best_match(&self, query: &str) -> anyhow::Result<OpenMeteoPlace> {
+        self.search(query, None, 1)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| MausamError::PlaceNotFound(query.to_string()).into())
+    }
+
+    /// Search the Open-Meteo geocoding API for up to `count` places matching `query`, only in
+    /// `country` when set.
+    async fn search(
+        &self,
+        query: &str,
+        country: Option<&str>,
+        count: usize,
+    ) -> anyhow::Result<Vec<OpenMeteoPlace>> {
+        let mut request = self.http.get(format!("{OPEN_METEO_GEOCODING_URL}/v1/search")).query(&[
+            ("name", query),
+            ("count", &count.to_string()),
+            ("format", "json"),
+        ]);
+        if let Some(code) = country {
+            request = request.query(&[("countryCode", code)]);
+        }
+        let result = self.http.send(request).await;
+        let response = result
+            .map_err(MausamError::from)
+            .with_context(|| format!("Failed to look up `{query}`"))?;
+        let geocoding: OpenMeteoGeocoding =
+            check_status(response, &Query::Place(query.to_string()))?.json().await.map_err(
+                |e| {
+                    anyhow!(MausamError::from(e))
+                        .context("Failed to deserialize the response body as JSON.")
+                },
+            )?;
+
+        Ok(geocoding.results.unwrap_or_default())
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "open-meteo"
+    }
+
+    async fn current(&self, query: &Query) -> anyhow::Result<Observation> {
+        let place = self.place(query).await?;
+        let data: OpenMeteoData = self
+            .get(
+                &place,
+                &[("current", CURRENT_VARS), ("daily", DAILY_VARS), ("forecast_days", "1")],
+            )
+            .await?;
+
+        observation(data, place.name, place.country_code)
+    }
+
+    async fn forecast(&self, query: &Query) -> anyhow::Result<Forecast> {
+        let place = self.place(query).await?;
+        let data: OpenMeteoForecast =
+            self.get(&place, &[("hourly", HOURLY_VARS), ("forecast_days", FORECAST_DAYS)]).await?;
+
+        forecast(data, place.name, place.country_code)
+    }
+
+    async fn geocode(
+        &self,
+        query: &str,
+        country: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Place>> {
+        Ok(self.search(query, country, limit).await?.into_iter().map(Place::from).collect())
+    }
+}
+
+impl OpenMeteo {
+    /// GET the forecast API at `place` with the extra `params` and deserialize the JSON
+    /// response body.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        place: &OpenMeteoPlace,
+        params: &[(&str, &str)],
+    ) -> anyhow::Result<T> {
+        let request = self
+            .http
+            .get(format!("{OPEN_METEO_API_URL}/v1/forecast"))
+            .query(&[
+                ("latitude", place.latitude.to_string().as_str()),
+                ("longitude", place.longitude.to_string().as_str()),
+                ("timezone", "auto"),
+                ("timeformat", "unixtime"),
+                ("wind_speed_unit", "ms"),
+            ])
+            .query(params);
+        let result = self.http.send(request).await;
+        let response = result
+            .map_err(MausamError::from)
+            .with_context(|| format!("Failed GET request for `{}`", place.name))?;
+
+        check_status(response, &Query::Place(place.name.clone()))?.json().await.map_err(|e| {
+            anyhow!(MausamError::from(e))
+                .context("Failed to deserialize the response body as JSON.")
+        })
+    }
+}
+
+/// Open-Meteo reports Celsius, which is converted to Kelvin to match the other providers.
+fn kelvin(celsius: f32) -> f32 {
+    Temperature::new(celsius, TempUnit::Celsius).to_kelvin().value
+}
+
+/// Map the Open-Meteo response `data` into an `Observation` for the place called `name`.
+fn observation(
+    data: OpenMeteoData,
+    name: String,
+    country: Option<String>,
+) -> anyhow::Result<Observation> {
+    let current = data.current;
+    let temp_min = *data.daily.temperature_2m_min.first().context("Missing daily minimum")?;
+    let temp_max = *data.daily.temperature_2m_max.first().context("Missing daily maximum")?;
+
+    Ok(Observation {
+        name,
+        country,
+        coord: Coord { lon: data.longitude, lat: data.latitude },
+        weather: wmo_weather(current.weather_code, current.is_day != 0),
+        main: Main {
+            temp: kelvin(current.temperature_2m),
+            feels_like: kelvin(current.apparent_temperature),
+            temp_min: kelvin(temp_min),
+            temp_max: kelvin(temp_max),
+            pressure: current.pressure_msl.round() as i32,
+            humidity: current.relative_humidity_2m,
+        },
+        visibility: current.visibility.map(|v| v.round() as i32),
+        wind: Wind { speed: current.wind_speed_10m, deg: current.wind_direction_10m },
+        clouds: Clouds { all: current.cloud_cover },
+        dt: current.time,
+        sunrise: data.daily.sunrise.first().copied(),
+        sunset: data.daily.sunset.first().copied(),
+        timezone: data.utc_offset_seconds,
+        stale: None,
+    })
+}
+
+/// Map the Open-Meteo hourly response `data` into a `Forecast` for the place called `name`.
+///
+/// # Errors
+///
+/// Returns [`MausamError::Decode`] if a column does not have one value per timestamp.
+fn forecast(
+    data: OpenMeteoForecast,
+    name: String,
+    country: Option<String>,
+) -> anyhow::Result<Forecast> {
+    let h = data.hourly;
+    let columns = [
+        ("temperature_2m", h.temperature_2m.len()),
+        ("relative_humidity_2m", h.relative_humidity_2m.len()),
+        ("apparent_temperature", h.apparent_temperature.len()),
+        ("precipitation_probability", h.precipitation_probability.len()),
+        ("precipitation", h.precipitation.len()),
+        ("is_day", h.is_day.len()),
+        ("weather_code", h.weather_code.len()),
+        ("cloud_cover", h.cloud_cover.len()),
+        ("pressure_msl", h.pressure_msl.len()),
+        ("wind_speed_10m", h.wind_speed_10m.len()),
+        ("wind_direction_10m", h.wind_direction_10m.len()),
+    ];
+    if let Some((column, len)) = columns.into_iter().find(|(_, len)| *len != h.time.len()) {
+        let message = format!("Expected {} hourly `{column}` values, got {len}", h.time.len());
+        return Err(MausamError::Decode(message.into()).into());
+    }
+
+    let slots = (0..h.time.len())
+        .map(|i| {
+            let temp = kelvin(h.temperature_2m[i]);
+            ForecastSlot {
+                dt: h.time[i],
+                weather: wmo_weather(h.weather_code[i], h.is_day[i] != 0),
+                main: Main {
+                    temp,
+                    feels_like: kelvin(h.apparent_temperature[i]),
+                    temp_min: temp,
+                    temp_max: temp,
+                    pressure: h.pressure_msl[i].round() as i32,
+                    humidity: h.relative_humidity_2m[i],
+                },
+                wind: Wind { speed: h.wind_speed_10m[i], deg: h.wind_direction_10m[i] },
+                clouds: Clouds { all: h.cloud_cover[i] },
+                pop: h.precipitation_probability[i].map(|p| p as f32 / 100.0),
+                precipitation: Some(h.precipitation[i]),
+            }
+        })
+        .collect();
+
+    Ok(Forecast {
+        name,
+        country,
+        coord: Coord { lon: data.longitude, lat: data.latitude },
+        timezone: data.utc_offset_seconds,
+        slots,
+        stale: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const OPEN_METEO_RESPONSE: &str = r#"{"latitude":48.86,"longitude":2.3399997,"generationtime_ms":0.0450611114501953,"utc_offset_seconds":3600,"timezone":"Europe/Paris","timezone_abbreviation":"CET","elevation":43.0,"current_units":{"time":"unixtime","interval":"seconds","temperature_2m":"°C","relative_humidity_2m":"%","apparent_temperature":"°C","is_day":"","weather_code":"wmo code","cloud_cover":"%","pressure_msl":"hPa","wind_speed_10m":"m/s","wind_direction_10m":"°","visibility":"m"},"current":{"time":1675343700,"interval":900,"temperature_2m":10.0,"relative_humidity_2m":72,"apparent_temperature":8.9,"is_day":1,"weather_code":3,"cloud_cover":100,"pressure_msl":1031.2,"wind_speed_10m":6.2,"wind_direction_10m":260,"visibility":24140.0},"daily_units":{"time":"unixtime","temperature_2m_max":"°C","temperature_2m_min":"°C","sunrise":"unixtime","sunset":"unixtime"},"daily":{"time":[1675292400],"temperature_2m_max":[10.8],"temperature_2m_min":[9.4],"sunrise":[1675322401],"sunset":[1675356505]}}"#;
+
+    #[test]
+    fn should_map_open_meteo_to_observation() {
+        let data: OpenMeteoData = serde_json::from_str(OPEN_METEO_RESPONSE)
+            .map_err(|e| anyhow!("Failed to convert to json from string: {e}"))
+            .unwrap();
+        let got = observation(data, "Paris".to_string(), Some("FR".to_string())).unwrap();
+
+        assert_eq!(got.name, "Paris");
+        assert_eq!(got.country.as_deref(), Some("FR"));
+        assert_eq!(got.weather.id, 804);
+        assert_eq!(got.weather.main, "Clouds");
+        assert_eq!(got.weather.icon, "04d");
+        assert!((got.main.temp - 283.15).abs() < 1e-3);
+        assert!((got.main.temp_min - 282.55).abs() < 1e-3);
+        assert!((got.main.temp_max - 283.95).abs() < 1e-3);
+        assert_eq!(got.main.pressure, 1031);
+        assert_eq!(got.main.humidity, 72);
+        assert_eq!(got.visibility, Some(24140));
+        assert_eq!(got.wind, Wind { speed: 6.2, deg: 260 });
+        assert_eq!(got.clouds, Clouds { all: 100 });
+        assert_eq!(got.dt, 1675343700);
+        assert_eq!(got.sunrise, Some(1675322401));
+        assert_eq!(got.sunset, Some(1675356505));
+        assert_eq!(got.timezone, 3600);
+    }
+
+    const OPEN_METEO_FORECAST_RESPONSE: &str = r#"{"latitude":48.86,"longitude":2.3399997,"generationtime_ms":0.1,"utc_offset_seconds":3600,"timezone":"Europe/Paris","timezone_abbreviation":"CET","elevation":43.0,"hourly_units":{"time":"unixtime"},"hourly":{"time":[1675335600,1675339200],"temperature_2m":[9.6,10.0],"relative_humidity_2m":[75,72],"apparent_temperature":[8.4,8.9],"precipitation_probability":[10,null],"precipitation":[0.0,0.2],"is_day":[1,1],"weather_code":[3,61],"cloud_cover":[100,98],"pressure_msl":[1031.4,1031.2],"wind_speed_10m":[5.9,6.2],"wind_direction_10m":[258,260]}}"#;
+
+    #[test]
+    fn should_map_open_meteo_forecast() {
+        let data: OpenMeteoForecast = serde_json::from_str(OPEN_METEO_FORECAST_RESPONSE)
+            .map_err(|e| anyhow!("Failed to convert to json from string: {e}"))
+            .unwrap();
+        let got = forecast(data, "Paris".to_string(), None).unwrap();
+
+        assert_eq!(got.slots.len(), 2);
+        assert_eq!(got.slots[0].dt, 1675335600);
+        assert_eq!(got.slots[0].pop, Some(0.1));
+        assert_eq!(got.slots[1].pop, None);
+        assert_eq!(got.slots[1].weather.main, "Rain");
+        assert_eq!(got.slots[1].main.temp, got.slots[1].main.temp_max);
+    }
+
+    #[test]
+    fn should_reject_ragged_open_meteo_forecast() {
+        let mut data: OpenMeteoForecast =
+            serde_json::from_str(OPEN_METEO_FORECAST_RESPONSE).unwrap();
+        data.hourly.weather_code.pop();
+
+        let err = forecast(data, "Paris".to_string(), None).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(MausamError::Decode(_))), "{err:?}");
+        let source = err.downcast_ref::<MausamError>().and_then(Error::source).unwrap();
+        assert_eq!(source.to_string(), "Expected 2 hourly `weather_code` values, got 1");
+    }
+}
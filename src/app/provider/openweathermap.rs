@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Context};
+use async_trait::async_trait;
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+
+use super::{status_error, Query, WeatherProvider};
+use crate::{
+    app::http::Http,
+    error::MausamError,
+    models::{
+        Forecast, Observation, OpenWeatherData, OpenWeatherError, OpenWeatherForecast,
+        OpenWeatherGeocodingEntry, Place,
+    },
+};
+
+/// Base URL of the `OpenWeatherMap` API.
+pub const OWM_API_URL: &str = "https://api.openweathermap.org";
+
+/// [`WeatherProvider`] backed by the `OpenWeatherMap` current weather API.
+/// [Reference](https://openweathermap.org/current)
+#[derive(Debug, Clone)]
+pub struct OpenWeatherMap {
+    api_key: String,
+    /// Language of the condition descriptions, English when unset.
+    lang: Option<String>,
+    http: Http,
+}
+
+impl OpenWeatherMap {
+    pub fn new(api_key: &str, lang: Option<String>, http: Http) -> Self {
+        Self { api_key: api_key.to_string(), lang, http }
+    }
+}
+
+#[async_trait]
+impl WeatherProvider for OpenWeatherMap {
+    fn name(&self) -> &'static str {
+        "openweathermap"
+    }
+
+    async fn current(&self, query: &Query) -> anyhow::Result<Observation> {
+        let data: OpenWeatherData = self.get("data/2.5/weather", query, &[]).await?;
+        Observation::try_from(data)
+    }
+
+    async fn forecast(&self, query: &Query) -> anyhow::Result<Forecast> {
+        let data: OpenWeatherForecast = self.get("data/2.5/forecast", query, &[]).await?;
+        Forecast::try_from(data)
+    }
+
+    async fn geocode(
+        &self,
+        query: &str,
+        country: Option<&str>,
+        limit: usize,
+    ) -> anyhow::Result<Vec<Place>> {
+        // The country goes after the name, e.g. `Paris,US`.
+        let query = Query::Place(match country {
+            Some(code) => format!("{query},{code}"),
+            None => query.to_string(),
+        });
+        let entries: Vec<OpenWeatherGeocodingEntry> =
+            self.get("geo/1.0/direct", &query, &[("limit", limit.to_string())]).await?;
+        Ok(entries.into_iter().map(Place::from).collect())
+    }
+}
+
+impl OpenWeatherMap {
+    /// GET the API `endpoint` at `query` with the extra `params` and deserialize the JSON
+    /// response body.
+    async fn get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        query: &Query,
+        params: &[(&str, String)],
+    ) -> anyhow::Result<T> {
+        let location = match query {
+            Query::Place(place) => vec![("q", place.clone())],
+            Query::Coords { lat, lon, .. } => {
+                vec![("lat", lat.to_string()), ("lon", lon.to_string())]
+            }
+        };
+        let mut request = self
+            .http
+            .get(format!("{OWM_API_URL}/{endpoint}"))
+            .query(&location)
+            .query(params)
+            .query(&[("appid", &self.api_key)]);
+        if let Some(lang) = &self.lang {
+            request = request.query(&[("lang", lang)]);
+        }
+        let result = self.http.send(request).await;
+        let response = result
+            .map_err(MausamError::from)
+            .with_context(|| format!("Failed GET request for `{query}`"))?;
+        let response = check_response(response, query)
+            .await
+            .with_context(|| format!("Failed GET request for `{query}`"))?;
+
+        response.json().await.map_err(|e| {
+            anyhow!(MausamError::from(e))
+                .context("Failed to deserialize the response body as JSON.")
+        })
+    }
+}
+
+/// Pass a successful `response` through, or turn an error status into a [`MausamError`] with
+/// the message from the `OpenWeatherMap` error body.
+async fn check_response(response: Response, query: &Query) -> anyhow::Result<Response> {
+    let status = response.status();
+    let err = match response.error_for_status_ref() {
+        Ok(_) => return Ok(response),
+        Err(err) => status_error(status, query).unwrap_or_else(|| MausamError::from(err)),
+    };
+
+    match response.json::<OpenWeatherError>().await {
+        Ok(body) => Err(anyhow!(err).context(format!("`OpenWeatherMap` says: {}", body.message))),
+        Err(_) => Err(err.into()),
+    }
+}
@@ -57,7 +57,7 @@ impl Temperature {
         Self { value, unit }
     }
 
-    pub fn to_fahrenheit(&self) -> Temperature {
+    pub fn to_fahrenheit(self) -> Temperature {
         match self.unit {
             TempUnit::Fahrenheit => Temperature { value: self.value, unit: TempUnit::Fahrenheit },
             // Convert degrees Celsius to degrees Fahrenheit. Formula - `(33.8°F − 32) × 5/9 = 1°C`
@@ -73,7 +73,7 @@ impl Temperature {
         }
     }
 
-    pub fn to_celsius(&self) -> Temperature {
+    pub fn to_celsius(self) -> Temperature {
         match self.unit {
             // Convert degrees Fahrenheit to degrees Celsius. Formula - `(33.8°F − 32) × 5/9 = 1°C`
             TempUnit::Fahrenheit => {
@@ -87,7 +87,16 @@ impl Temperature {
         }
     }
 
-    pub fn to_kelvin(&self) -> Temperature {
+    /// Convert to the temperature `unit`.
+    pub fn to_unit(self, unit: TempUnit) -> Temperature {
+        match unit {
+            TempUnit::Celsius => self.to_celsius(),
+            TempUnit::Fahrenheit => self.to_fahrenheit(),
+            TempUnit::Kelvin => self.to_kelvin(),
+        }
+    }
+
+    pub fn to_kelvin(self) -> Temperature {
         match self.unit {
             // Convert degrees Fahrenheit to Kelvin.
             TempUnit::Fahrenheit => Temperature {
@@ -196,7 +205,7 @@ pub mod tests {
                 TempUnit::Celsius => temp.value,
                 TempUnit::Kelvin => temp.value - 273.15_f32,
             };
-            (temp.to_celsius().value - expected).abs() < std::f32::EPSILON
+            (temp.to_celsius().value - expected).abs() < f32::EPSILON
         }
         quickcheck(prop as fn(Temperature) -> bool);
     }
@@ -209,7 +218,7 @@ pub mod tests {
                 TempUnit::Celsius => (temp.value * (9f32 / 5f32)) + 32f32,
                 TempUnit::Kelvin => (temp.value - 273.15_f32) * (9f32 / 5f32) + 32f32,
             };
-            (temp.to_fahrenheit().value - expected).abs() < std::f32::EPSILON
+            (temp.to_fahrenheit().value - expected).abs() < f32::EPSILON
         }
         quickcheck(prop as fn(Temperature) -> bool);
     }
@@ -221,7 +230,7 @@ pub mod tests {
                 TempUnit::Celsius => temp.value + 273.15_f32,
                 TempUnit::Kelvin => temp.value,
             };
-            (temp.to_kelvin().value - expected).abs() < std::f32::EPSILON
+            (temp.to_kelvin().value - expected).abs() < f32::EPSILON
         }
         quickcheck(prop as fn(Temperature) -> bool);
     }
@@ -0,0 +1,232 @@
+//! mod `template` fills the notification templates, `summary_template` and `body_template`.
+//!
+//! A template is text with `{placeholder}`s, e.g. `{name} {temp}{unit}, feels like
+//! {feels_like}{unit}`. Write `{{` and `}}` for literal braces. The placeholders are listed in
+//! [`PLACEHOLDERS`], values missing from the observation render as `-`. `{as_of}` is empty
+//! unless the observation is stale.
+
+use std::collections::BTreeMap;
+
+use anyhow::bail;
+use chrono::DateTime;
+
+use super::{capitalize, round_f32_dp, DisplayUnits, TempUnit, Temperature};
+use crate::{display_tempunit, models::Observation};
+
+/// Default `summary_template`, e.g. `Paris 10.03°C`.
+pub const DEFAULT_SUMMARY: &str = "{name} {temp}{unit}";
+
+/// Default `body_template`, e.g. `Overcast clouds... 9°C / 11°C` and the wind, pressure and
+/// visibility on a second line.
+pub const DEFAULT_BODY: &str = "{description}... {temp_min}{unit} / {temp_max}{unit}\n\
+                                Wind {wind} {wind_deg}°, Pressure {pressure}, Visibility {visibility}";
+
+/// [`DEFAULT_BODY`] for an observation without visibility, which some providers do not report.
+const DEFAULT_BODY_WITHOUT_VISIBILITY: &str = "{description}... {temp_min}{unit} / \
+                                               {temp_max}{unit}\n\
+                                               Wind {wind} {wind_deg}°, Pressure {pressure}";
+
+/// Every placeholder, with what it stands for.
+pub const PLACEHOLDERS: [(&str, &str); 27] = [
+    ("name", "place name"),
+    ("country", "country code"),
+    ("lat", "latitude"),
+    ("lon", "longitude"),
+    ("condition", "condition group, e.g. `Clouds`"),
+    ("condition_id", "`OpenWeatherMap` condition code"),
+    ("description", "capitalized condition, e.g. `Broken clouds`"),
+    ("icon", "`OpenWeatherMap` icon code"),
+    ("temp", "temperature"),
+    ("feels_like", "perceived temperature"),
+    ("temp_min", "minimum temperature, rounded down"),
+    ("temp_max", "maximum temperature, rounded up"),
+    ("unit", "temperature unit, e.g. `°C`"),
+    ("humidity", "relative humidity, in %"),
+    ("pressure", "pressure, e.g. `1021 hPa`"),
+    ("wind", "wind speed, e.g. `4.2 m/s`"),
+    ("wind_speed", "wind speed, without unit"),
+    ("wind_deg", "wind direction, in degrees"),
+    ("wind_dir", "wind direction, e.g. `NW`"),
+    ("clouds", "cloudiness, in %"),
+    ("visibility", "visibility, e.g. `10.0 km`"),
+    ("time", "local time of the observation, e.g. `14:05`"),
+    ("dt", "Unix timestamp of the observation"),
+    ("sunrise", "local sunrise time"),
+    ("sunset", "local sunset time"),
+    ("timezone", "shift from UTC, in seconds"),
+    ("as_of", "age of a stale observation, e.g. `42 min ago`, empty otherwise"),
+];
+
+/// Points of the compass, clockwise from north.
+const COMPASS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+
+/// The default `body_template` for `data`, leaving out the visibility when it is missing.
+pub fn default_body(data: &Observation) -> &'static str {
+    if data.visibility.is_some() {
+        DEFAULT_BODY
+    } else {
+        DEFAULT_BODY_WITHOUT_VISIBILITY
+    }
+}
+
+/// Fill the `{placeholder}`s of `template` with `values`.
+///
+/// # Errors
+///
+/// Returns an error on an unknown placeholder or an unmatched brace.
+pub fn render(template: &str, values: &BTreeMap<&str, String>) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let Some((name, rest)) = chars.as_str().split_once('}') else {
+                    bail!("Unclosed `{{` in template `{template}`");
+                };
+                let Some(value) = values.get(name) else {
+                    bail!(
+                        "Unknown placeholder `{{{name}}}` in template `{template}`, known ones: {}",
+                        PLACEHOLDERS.map(|(name, _)| name).join(", ")
+                    );
+                };
+                out.push_str(value);
+                chars = rest.chars();
+            }
+            '}' => bail!("Unmatched `}}` in template `{template}`, write `}}}}` for a brace"),
+            c => out.push(c),
+        }
+    }
+
+    Ok(out)
+}
+
+/// The value of every placeholder for `data`, displayed in `units`.
+///
+/// # Errors
+///
+/// Returns an error if a temperature cannot be rounded.
+pub fn values(
+    data: &Observation,
+    units: &DisplayUnits,
+) -> anyhow::Result<BTreeMap<&'static str, String>> {
+    let main = &data.main;
+    let temp = |kelvin: f32| Temperature::new(kelvin, TempUnit::Kelvin).to_unit(units.temp);
+    let rounded = |kelvin: f32| round_f32_dp(temp(kelvin).value, 2).map(|t| units.number(t));
+    let local_time = |timestamp: i64| {
+        DateTime::from_timestamp(timestamp + i64::from(data.timezone), 0)
+            .map_or_else(|| "-".to_string(), |time| time.format("%H:%M").to_string())
+    };
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let wind_dir = COMPASS[(f64::from(data.wind.deg.rem_euclid(360)) / 22.5).round() as usize % 16];
+
+    #[allow(clippy::cast_precision_loss)]
+    let values = BTreeMap::from([
+        ("name", data.name.clone()),
+        ("country", data.country.clone().unwrap_or_else(|| "-".to_string())),
+        ("lat", data.coord.lat.to_string()),
+        ("lon", data.coord.lon.to_string()),
+        ("condition", data.weather.main.clone()),
+        ("condition_id", data.weather.id.to_string()),
+        ("description", capitalize(&data.weather.description)),
+        ("icon", data.weather.icon.clone()),
+        ("temp", rounded(main.temp)?),
+        ("feels_like", rounded(main.feels_like)?),
+        ("temp_min", temp(main.temp_min).value.floor().to_string()),
+        ("temp_max", temp(main.temp_max).value.ceil().to_string()),
+        ("unit", display_tempunit!(units.temp).to_string()),
+        ("humidity", main.humidity.to_string()),
+        ("pressure", units.pressure(main.pressure as f32)),
+        ("wind", units.speed(data.wind.speed)),
+        ("wind_speed", units.speed_value(data.wind.speed)),
+        ("wind_deg", data.wind.deg.to_string()),
+        ("wind_dir", wind_dir.to_string()),
+        ("clouds", data.clouds.all.to_string()),
+        (
+            "visibility",
+            data.visibility.map_or_else(|| "-".to_string(), |v| units.distance(v as f32)),
+        ),
+        ("time", local_time(data.dt)),
+        ("dt", data.dt.to_string()),
+        ("sunrise", data.sunrise.map_or_else(|| "-".to_string(), local_time)),
+        ("sunset", data.sunset.map_or_else(|| "-".to_string(), local_time)),
+        ("timezone", data.timezone.to_string()),
+        ("as_of", data.stale.as_ref().map_or_else(String::new, |stale| stale.as_of.clone())),
+    ]);
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        config::Units,
+        models::{Observation, OpenWeatherData},
+    };
+
+    fn london() -> BTreeMap<&'static str, String> {
+        let data = Observation::try_from(OpenWeatherData::default()).unwrap();
+        values(&data, &DisplayUnits::from(Units::Metric)).unwrap()
+    }
+
+    #[test]
+    fn should_fill_placeholders() {
+        let values = london();
+        let got = render("{name}: {temp}{unit}, feels like {feels_like}{unit}", &values).unwrap();
+        assert_eq!(got, "London: 7.19°C, feels like 3.61°C");
+
+        let got = render("{wind} from the {wind_dir}, sun {sunrise}-{sunset} {{ok}}", &values);
+        assert_eq!(got.unwrap(), "6.2 m/s from the WNW, sun 07:42-16:44 {ok}");
+    }
+
+    #[test]
+    fn should_cover_every_placeholder() {
+        let values = london();
+        let names: Vec<&str> = PLACEHOLDERS.iter().map(|(name, _)| *name).collect();
+        assert_eq!(values.keys().copied().collect::<Vec<_>>(), {
+            let mut names = names.clone();
+            names.sort_unstable();
+            names
+        });
+        assert!(render(DEFAULT_SUMMARY, &values).is_ok());
+        assert!(render(DEFAULT_BODY, &values).is_ok());
+    }
+
+    #[test]
+    fn should_leave_out_missing_visibility_by_default() {
+        let mut data = Observation::try_from(OpenWeatherData::default()).unwrap();
+        let units = DisplayUnits::from(Units::Metric);
+        let body = render(default_body(&data), &values(&data, &units).unwrap()).unwrap();
+        assert!(body.ends_with("Pressure 1021 hPa, Visibility 10.0 km"), "{body}");
+
+        data.visibility = None;
+        let values = values(&data, &units).unwrap();
+        assert_eq!(values["visibility"], "-");
+        assert_eq!(values["as_of"], "");
+        let body = render(default_body(&data), &values).unwrap();
+        assert!(body.ends_with("Wind 6.2 m/s 300°, Pressure 1021 hPa"), "{body}");
+    }
+
+    #[test]
+    fn should_reject_invalid_templates() {
+        let values = london();
+        let err = render("{nmae}", &values).unwrap_err().to_string();
+        assert!(err.starts_with("Unknown placeholder `{nmae}`"), "{err}");
+        assert!(render("{name", &values).is_err());
+        assert!(render("name}", &values).is_err());
+    }
+}
@@ -0,0 +1,479 @@
+//! mod `units` models the quantities reported besides temperature, in the spirit of
+//! [`Temperature`](super::Temperature) and [`TempUnit`].
+//!
+//! Providers report in the `OpenWeatherMap` "standard" set: wind speed in m/s, pressure in hPa,
+//! visibility in metres and precipitation in mm. The unit system from `--units` gives each
+//! quantity a default display unit, which `wind_unit`, `pressure_unit` and `visibility_unit`
+//! in the config file override. Decimals are written with a comma in languages that do so.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::TempUnit;
+use crate::config::{Config, Units};
+
+/// Generate a quantity struct with a `value` in a `unit`, whose unit type has a `per_base`
+/// factor from the provider's unit.
+macro_rules! quantity {
+    ($(#[$doc:meta])* $name:ident, $unit:ident) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        pub struct $name {
+            pub value: f32,
+            pub unit: $unit,
+        }
+
+        impl $name {
+            pub fn new(value: f32, unit: $unit) -> Self {
+                Self { value, unit }
+            }
+
+            /// Convert to the `unit`.
+            pub fn to_unit(self, unit: $unit) -> Self {
+                Self { value: self.value / self.unit.per_base() * unit.per_base(), unit }
+            }
+        }
+
+        impl From<$name> for f32 {
+            fn from(quantity: $name) -> f32 {
+                quantity.value
+            }
+        }
+
+        /// The value and unit, e.g. `4.2 m/s`, honouring the precision of the formatter.
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match f.precision() {
+                    Some(precision) => write!(f, "{:.precision$} {}", self.value, self.unit),
+                    None => write!(f, "{} {}", self.value, self.unit),
+                }
+            }
+        }
+    };
+}
+
+quantity!(
+    /// A wind speed.
+    Speed,
+    SpeedUnit
+);
+quantity!(
+    /// An atmospheric pressure.
+    Pressure,
+    PressureUnit
+);
+quantity!(
+    /// A distance, such as the visibility.
+    Distance,
+    DistanceUnit
+);
+quantity!(
+    /// A precipitation volume, as the height of water over the slot.
+    Precipitation,
+    PrecipitationUnit
+);
+
+/// Unit of a wind speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpeedUnit {
+    /// Metres per second.
+    Ms,
+    /// Kilometres per hour.
+    Kmh,
+    /// Miles per hour.
+    Mph,
+    /// Nautical miles per hour.
+    Knots,
+}
+
+impl SpeedUnit {
+    /// How many of this unit make one metre per second.
+    fn per_base(self) -> f32 {
+        match self {
+            Self::Ms => 1.0,
+            Self::Kmh => 3.6,
+            Self::Mph => 1.0 / 0.447_04,
+            Self::Knots => 3600.0 / 1852.0,
+        }
+    }
+}
+
+impl fmt::Display for SpeedUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ms => write!(f, "m/s"),
+            Self::Kmh => write!(f, "km/h"),
+            Self::Mph => write!(f, "mph"),
+            Self::Knots => write!(f, "kn"),
+        }
+    }
+}
+
+/// Unit of an atmospheric pressure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PressureUnit {
+    /// Hectopascals, or millibars.
+    Hpa,
+    /// Inches of mercury.
+    Inhg,
+    /// Millimetres of mercury.
+    Mmhg,
+}
+
+impl PressureUnit {
+    /// How many of this unit make one hectopascal.
+    fn per_base(self) -> f32 {
+        match self {
+            Self::Hpa => 1.0,
+            Self::Inhg => 1.0 / 33.863_89,
+            Self::Mmhg => 1.0 / 1.333_224,
+        }
+    }
+
+    /// Decimals worth showing, an inch of mercury is about 34 hPa.
+    pub fn decimals(self) -> usize {
+        match self {
+            Self::Hpa | Self::Mmhg => 0,
+            Self::Inhg => 2,
+        }
+    }
+}
+
+impl fmt::Display for PressureUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Hpa => write!(f, "hPa"),
+            Self::Inhg => write!(f, "inHg"),
+            Self::Mmhg => write!(f, "mmHg"),
+        }
+    }
+}
+
+/// Unit of a distance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceUnit {
+    /// Metres.
+    M,
+    /// Kilometres.
+    Km,
+    /// Miles.
+    Mi,
+}
+
+impl DistanceUnit {
+    /// How many of this unit make one metre.
+    fn per_base(self) -> f32 {
+        match self {
+            Self::M => 1.0,
+            Self::Km => 1.0 / 1000.0,
+            Self::Mi => 1.0 / 1609.344,
+        }
+    }
+}
+
+impl fmt::Display for DistanceUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::M => write!(f, "m"),
+            Self::Km => write!(f, "km"),
+            Self::Mi => write!(f, "mi"),
+        }
+    }
+}
+
+/// Unit of a precipitation volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrecipitationUnit {
+    /// Millimetres.
+    Mm,
+    /// Inches.
+    In,
+}
+
+impl PrecipitationUnit {
+    /// How many of this unit make one millimetre.
+    fn per_base(self) -> f32 {
+        match self {
+            Self::Mm => 1.0,
+            Self::In => 1.0 / 25.4,
+        }
+    }
+}
+
+impl fmt::Display for PrecipitationUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mm => write!(f, "mm"),
+            Self::In => write!(f, "in"),
+        }
+    }
+}
+
+/// Languages writing a decimal comma, by `OpenWeatherMap` language code.
+const DECIMAL_COMMA: [&str; 42] = [
+    "af", "al", "az", "bg", "ca", "cs", "cz", "da", "de", "el", "es", "eu", "fi", "fr", "gl", "hr",
+    "hu", "id", "it", "la", "lt", "lv", "mk", "nb", "nl", "no", "pl", "pt", "ro", "ru", "se", "sk",
+    "sl", "sp", "sq", "sr", "sv", "tr", "ua", "uk", "vi", "nn",
+];
+
+/// Decimal separator of the language `lang`, e.g. `,` for `de` and `.` for `hi`.
+pub fn decimal_separator(lang: &str) -> char {
+    let lang = lang.split('_').next().unwrap_or_default();
+    if DECIMAL_COMMA.contains(&lang) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+/// The unit each quantity is displayed in, and the decimal separator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayUnits {
+    pub temp: TempUnit,
+    pub speed: SpeedUnit,
+    pub pressure: PressureUnit,
+    pub distance: DistanceUnit,
+    pub precipitation: PrecipitationUnit,
+    pub decimal: char,
+}
+
+impl From<Units> for DisplayUnits {
+    /// Defaults of the unit system, matching what `OpenWeatherMap` reports in it, except for
+    /// visibility which it always reports in metres.
+    fn from(units: Units) -> Self {
+        match units {
+            Units::Metric | Units::Standard => Self {
+                temp: units.temp_unit(),
+                speed: SpeedUnit::Ms,
+                pressure: PressureUnit::Hpa,
+                distance: DistanceUnit::Km,
+                precipitation: PrecipitationUnit::Mm,
+                decimal: '.',
+            },
+            Units::Imperial => Self {
+                temp: units.temp_unit(),
+                speed: SpeedUnit::Mph,
+                pressure: PressureUnit::Inhg,
+                distance: DistanceUnit::Mi,
+                precipitation: PrecipitationUnit::In,
+                decimal: '.',
+            },
+        }
+    }
+}
+
+impl From<&Config> for DisplayUnits {
+    /// Defaults of `config.units`, overridden by the per quantity units of `config`, with the
+    /// decimal separator of `config.lang`.
+    fn from(config: &Config) -> Self {
+        let units = Self::from(config.units);
+        Self {
+            speed: config.wind_unit.unwrap_or(units.speed),
+            pressure: config.pressure_unit.unwrap_or(units.pressure),
+            distance: config.visibility_unit.unwrap_or(units.distance),
+            decimal: config.lang.as_deref().map_or(units.decimal, decimal_separator),
+            ..units
+        }
+    }
+}
+
+impl DisplayUnits {
+    /// `number` written with the decimal separator, e.g. `10,03` in German.
+    pub fn number(&self, number: impl fmt::Display) -> String {
+        let number = number.to_string();
+        match self.decimal {
+            '.' => number,
+            decimal => number.replace('.', &decimal.to_string()),
+        }
+    }
+
+    /// Wind speed in m/s for humans, e.g. `9.4 mph`.
+    pub fn speed(&self, speed: f32) -> String {
+        format!("{} {}", self.speed_value(speed), self.speed)
+    }
+
+    /// Wind speed in m/s as a number in the display unit, e.g. `9.4`.
+    pub fn speed_value(&self, speed: f32) -> String {
+        self.number(format!("{:.1}", Speed::new(speed, SpeedUnit::Ms).to_unit(self.speed).value))
+    }
+
+    /// Pressure in hPa for humans, e.g. `30.15 inHg`.
+    pub fn pressure(&self, pressure: f32) -> String {
+        format!("{} {}", self.pressure_value(pressure), self.pressure)
+    }
+
+    /// Pressure in hPa as a number in the display unit, e.g. `30.15`.
+    pub fn pressure_value(&self, pressure: f32) -> String {
+        let decimals = self.pressure.decimals();
+        let pressure = Pressure::new(pressure, PressureUnit::Hpa).to_unit(self.pressure);
+        self.number(format!("{:.decimals$}", pressure.value))
+    }
+
+    /// Distance in metres for humans, e.g. `6.2 mi`.
+    pub fn distance(&self, distance: f32) -> String {
+        format!("{} {}", self.distance_value(distance), self.distance)
+    }
+
+    /// Distance in metres as a number in the display unit, e.g. `6.2`.
+    pub fn distance_value(&self, distance: f32) -> String {
+        let distance = Distance::new(distance, DistanceUnit::M).to_unit(self.distance);
+        self.number(format!("{:.1}", distance.value))
+    }
+
+    /// Precipitation in mm for humans, e.g. `0.05 in`.
+    pub fn precipitation(&self, precipitation: f32) -> String {
+        format!("{} {}", self.precipitation_value(precipitation), self.precipitation)
+    }
+
+    /// Precipitation in mm as a number in the display unit, e.g. `0.05`.
+    pub fn precipitation_value(&self, precipitation: f32) -> String {
+        let decimals = match self.precipitation {
+            PrecipitationUnit::Mm => 1,
+            PrecipitationUnit::In => 2,
+        };
+        let precipitation = Precipitation::new(precipitation, PrecipitationUnit::Mm);
+        self.number(format!("{:.decimals$}", precipitation.to_unit(self.precipitation).value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quickcheck::{quickcheck, Arbitrary, Gen};
+
+    use super::*;
+
+    /// Implement `Arbitrary` for a quantity and its unit, picking among typical values.
+    macro_rules! arbitrary {
+        ($name:ident, $unit:ident, [$($variant:ident),+], $values:expr) => {
+            impl Arbitrary for $unit {
+                fn arbitrary(g: &mut Gen) -> Self {
+                    *g.choose(&[$($unit::$variant),+]).unwrap()
+                }
+            }
+
+            impl Arbitrary for $name {
+                fn arbitrary(g: &mut Gen) -> Self {
+                    $name { value: *g.choose($values).unwrap(), unit: $unit::arbitrary(g) }
+                }
+            }
+        };
+    }
+
+    arbitrary!(Speed, SpeedUnit, [Ms, Kmh, Mph, Knots], &[0.0, 0.5, 4.2, 15.0, 42.0, 113.0]);
+    arbitrary!(Pressure, PressureUnit, [Hpa, Inhg, Mmhg], &[870.0, 1013.25, 1084.0, 29.92]);
+    arbitrary!(Distance, DistanceUnit, [M, Km, Mi], &[0.0, 50.0, 1000.0, 10000.0, 6.2]);
+    arbitrary!(Precipitation, PrecipitationUnit, [Mm, In], &[0.0, 0.2, 1.0, 25.4, 300.0]);
+
+    /// Whether `got` equals `expected` up to the precision of `f32`.
+    fn close(got: f32, expected: f32) -> bool {
+        (got - expected).abs() <= 1e-5 * expected.abs().max(1.0)
+    }
+
+    #[test]
+    fn test_speed_round_trip() {
+        fn prop(speed: Speed, unit: SpeedUnit) -> bool {
+            let got = speed.to_unit(unit).to_unit(speed.unit);
+            got.unit == speed.unit && close(got.value, speed.value)
+        }
+        quickcheck(prop as fn(Speed, SpeedUnit) -> bool);
+    }
+
+    #[test]
+    fn test_pressure_round_trip() {
+        fn prop(pressure: Pressure, unit: PressureUnit) -> bool {
+            let got = pressure.to_unit(unit).to_unit(pressure.unit);
+            got.unit == pressure.unit && close(got.value, pressure.value)
+        }
+        quickcheck(prop as fn(Pressure, PressureUnit) -> bool);
+    }
+
+    #[test]
+    fn test_distance_round_trip() {
+        fn prop(distance: Distance, unit: DistanceUnit) -> bool {
+            let got = distance.to_unit(unit).to_unit(distance.unit);
+            got.unit == distance.unit && close(got.value, distance.value)
+        }
+        quickcheck(prop as fn(Distance, DistanceUnit) -> bool);
+    }
+
+    #[test]
+    fn test_precipitation_round_trip() {
+        fn prop(precipitation: Precipitation, unit: PrecipitationUnit) -> bool {
+            let got = precipitation.to_unit(unit).to_unit(precipitation.unit);
+            got.unit == precipitation.unit && close(got.value, precipitation.value)
+        }
+        quickcheck(prop as fn(Precipitation, PrecipitationUnit) -> bool);
+    }
+
+    #[test]
+    fn test_to_ms_conversion() {
+        fn prop(speed: Speed) -> bool {
+            let expected = match speed.unit {
+                SpeedUnit::Ms => speed.value,
+                SpeedUnit::Kmh => speed.value / 3.6,
+                SpeedUnit::Mph => speed.value * 0.447_04,
+                SpeedUnit::Knots => speed.value * 1852.0 / 3600.0,
+            };
+            close(speed.to_unit(SpeedUnit::Ms).value, expected)
+        }
+        quickcheck(prop as fn(Speed) -> bool);
+    }
+
+    #[test]
+    fn should_display_quantities() {
+        assert_eq!(Speed::new(4.2, SpeedUnit::Ms).to_string(), "4.2 m/s");
+        assert_eq!(format!("{:.0}", Pressure::new(1013.25, PressureUnit::Hpa)), "1013 hPa");
+        assert_eq!(format!("{:.1}", Distance::new(10000.0, DistanceUnit::M)), "10000.0 m");
+        assert_eq!(format!("{:.2}", Precipitation::new(25.4, PrecipitationUnit::Mm)), "25.40 mm");
+        assert_eq!(f32::from(Speed::new(36.0, SpeedUnit::Kmh).to_unit(SpeedUnit::Ms)), 10.0);
+    }
+
+    #[test]
+    fn should_display_in_unit_system() {
+        let imperial = DisplayUnits::from(Units::Imperial);
+        assert_eq!(imperial.speed(4.2), "9.4 mph");
+        assert_eq!(imperial.pressure(1021.0), "30.15 inHg");
+        assert_eq!(imperial.distance(10000.0), "6.2 mi");
+        assert_eq!(imperial.precipitation(1.2), "0.05 in");
+
+        let metric = DisplayUnits::from(Units::Metric);
+        assert_eq!(metric.speed(4.2), "4.2 m/s");
+        assert_eq!(metric.pressure(1021.0), "1021 hPa");
+        assert_eq!(metric.distance(10000.0), "10.0 km");
+        assert_eq!(metric.precipitation(1.2), "1.2 mm");
+    }
+
+    #[test]
+    fn should_override_unit_system() {
+        let config = Config {
+            units: Units::Imperial,
+            wind_unit: Some(SpeedUnit::Knots),
+            pressure_unit: Some(PressureUnit::Mmhg),
+            ..Config::default()
+        };
+        let units = DisplayUnits::from(&config);
+        assert_eq!(units.temp, TempUnit::Fahrenheit);
+        assert_eq!(units.speed(10.0), "19.4 kn");
+        assert_eq!(units.pressure(1013.25), "760 mmHg");
+        assert_eq!(units.distance(1609.344), "1.0 mi");
+    }
+
+    #[test]
+    fn should_write_decimals_per_language() {
+        let german = Config { lang: Some("de".to_string()), ..Config::default() };
+        let units = DisplayUnits::from(&german);
+        assert_eq!(units.speed(4.2), "4,2 m/s");
+        assert_eq!(units.number(10.03), "10,03");
+
+        let hindi = Config { lang: Some("hi".to_string()), ..Config::default() };
+        assert_eq!(DisplayUnits::from(&hindi).speed(4.2), "4.2 m/s");
+        assert_eq!(decimal_separator("pt_br"), ',');
+        assert_eq!(decimal_separator("zh_cn"), '.');
+    }
+}
@@ -0,0 +1,110 @@
+//! mod `cache` persists small JSON files between runs, under `$XDG_CACHE_HOME/mausam` (usually
+//! `~/.cache/mausam`) unless `cache.dir` is configured.
+//!
+//! Every value is stored with the time it was saved, so callers can decide how stale is too
+//! stale. Writes go to a temporary file that is renamed over the target, so a concurrent run
+//! never reads a half written file.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{app::APP_NAME, config::CacheConfig};
+
+/// A cached value along with the time it was saved.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stamped<T> {
+    /// Unix timestamp, in seconds, of when the value was saved.
+    pub saved_at: i64,
+    pub value: T,
+}
+
+impl<T> Stamped<T> {
+    /// Seconds elapsed between saving the value and `now`.
+    pub fn age(&self, now: i64) -> i64 {
+        now - self.saved_at
+    }
+
+    /// Whether the value is younger than `ttl` seconds at `now`.
+    pub fn is_fresh(&self, now: i64, ttl: u64) -> bool {
+        u64::try_from(self.age(now)).is_ok_and(|age| age < ttl)
+    }
+}
+
+/// Directory holding the cache files.
+pub fn dir(config: &CacheConfig) -> Option<PathBuf> {
+    config.dir.clone().or_else(|| dirs::cache_dir().map(|dir| dir.join(APP_NAME)))
+}
+
+/// Read the value cached at `path`, or `None` if nothing is cached yet.
+///
+/// # Errors
+///
+/// Returns an error if the file exists but cannot be read or parsed.
+pub fn read<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Option<Stamped<T>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let s = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read cache file `{}`", path.display()))?;
+    let stamped = serde_json::from_str(&s)
+        .with_context(|| format!("Failed to parse cache file `{}`", path.display()))?;
+
+    Ok(Some(stamped))
+}
+
+/// Atomically cache `value` at `path`, stamped with `now`.
+///
+/// # Errors
+///
+/// Returns an error if the cache directory or file cannot be written.
+pub fn write<T: Serialize>(path: &Path, value: &T, now: i64) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create cache dir `{}`", parent.display()))?;
+    }
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    let json = serde_json::to_string(&Stamped { saved_at: now, value })?;
+
+    fs::write(&tmp, json)
+        .with_context(|| format!("Failed to write cache file `{}`", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("Failed to write cache file `{}`", path.display()))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_round_trip_stamped_value() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("value.json");
+
+        assert_eq!(read::<String>(&path).unwrap(), None);
+
+        write(&path, &"Paris".to_string(), 1_000).unwrap();
+        let got = read::<String>(&path).unwrap().unwrap();
+
+        assert_eq!(got, Stamped { saved_at: 1_000, value: "Paris".to_string() });
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn should_expire_after_ttl() {
+        let stamped = Stamped { saved_at: 1_000, value: () };
+
+        assert!(stamped.is_fresh(1_059, 60));
+        assert!(!stamped.is_fresh(1_060, 60));
+        assert!(!stamped.is_fresh(1_000, 0));
+        assert_eq!(stamped.age(1_060), 60);
+    }
+}
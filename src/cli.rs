@@ -1,8 +1,147 @@
-use clap::Parser;
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{
+    app::{ProviderKind, Strategy},
+    config::Units,
+    output::Format,
+};
 
 #[derive(Debug, Clone, Parser)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     // #[arg(default_value = "London")]
+    /// Place name, or coordinates as `lat,lon`.
+    #[arg(allow_hyphen_values = true)]
     pub place: Option<String>,
-    // pub coord: Option<Coordinates>,
+    /// Latitude in decimal degrees, queried together with `--lon`.
+    #[arg(long, allow_negative_numbers = true, requires = "lon", global = true)]
+    pub lat: Option<f64>,
+    /// Longitude in decimal degrees, queried together with `--lat`.
+    #[arg(long, allow_negative_numbers = true, requires = "lat", global = true)]
+    pub lon: Option<f64>,
+    /// List the forecast slots for the next N hours instead of the current weather.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=120))]
+    pub hours: Option<u32>,
+    /// Weather provider to query. Defaults to `openweathermap` when `WEATHER_API_KEY` is set,
+    /// otherwise to the keyless `open-meteo`.
+    #[arg(long, value_enum, global = true)]
+    pub provider: Option<ProviderKind>,
+    /// Unit system to display temperature, wind speed, pressure and visibility in.
+    #[arg(long, value_enum, global = true)]
+    pub units: Option<Units>,
+    /// Output format. Defaults to `json` for the weather and `text` otherwise.
+    #[arg(long, value_enum, global = true)]
+    pub format: Option<Format>,
+    /// Language of the condition descriptions, e.g. `de` or `hi`. Defaults to the system
+    /// locale.
+    #[arg(long, value_name = "LANG", global = true)]
+    pub lang: Option<String>,
+    /// Locate the machine offline with this `MaxMind` `GeoLite2` City database.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub geoip_db: Option<PathBuf>,
+    /// Look up the current location again instead of using the cached one.
+    #[arg(long, global = true)]
+    pub refresh_location: bool,
+    /// Location strategies to try, in order, e.g. `--locate-with cache,geoip`.
+    #[arg(long, value_enum, value_name = "STRATEGY", value_delimiter = ',', global = true)]
+    pub locate_with: Option<Vec<Strategy>>,
+    /// Fetch these named locations from the config file, e.g. `--location office,home`.
+    #[arg(long, value_name = "NAME", value_delimiter = ',', conflicts_with = "all", global = true)]
+    pub location: Option<Vec<String>>,
+    /// Fetch every named location from the config file.
+    #[arg(long, global = true)]
+    pub all: bool,
+    /// Show one notification for all locations instead of one per location.
+    #[arg(long, global = true)]
+    pub combine: bool,
+    /// Only notify about the current weather when it changed since the last notification.
+    #[arg(long, global = true)]
+    pub changes: bool,
+    /// Template of the weather notification summary, e.g. `'{name} {temp}{unit}'`.
+    #[arg(long, value_name = "TEMPLATE", global = true)]
+    pub template: Option<String>,
+    /// Template of the weather notification body, e.g. `'{description}, sunset {sunset}'`.
+    #[arg(long, value_name = "TEMPLATE", global = true)]
+    pub body_template: Option<String>,
+    /// Fetch fresh weather instead of a cached response.
+    #[arg(long, conflicts_with = "max_age", global = true)]
+    pub no_cache: bool,
+    /// Serve cached weather responses up to this many seconds old. Defaults to 600.
+    #[arg(long, value_name = "SECS", global = true)]
+    pub max_age: Option<u64>,
+    /// Serve the last cached weather, whatever its age, without going online.
+    #[arg(long, conflicts_with = "no_cache", global = true)]
+    pub offline: bool,
+    /// Path to the config file. Defaults to `~/.config/mausam/config.toml`.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+}
+
+impl Cli {
+    /// The place passed on the command line, for the top-level command or a subcommand.
+    pub fn place(&self) -> Option<&str> {
+        match &self.command {
+            Some(Command::Forecast { place, .. }) => place.as_deref(),
+            Some(Command::Locate { .. }) => None,
+            Some(Command::Config) | None => self.place.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum Command {
+    /// Print a per-day summary of the forecast for the next few days.
+    Forecast {
+        /// Place name, or coordinates as `lat,lon`.
+        #[arg(allow_hyphen_values = true)]
+        place: Option<String>,
+        /// Send one notification per day.
+        #[arg(long)]
+        notify: bool,
+    },
+    /// List the places matching a name, with their state, country and coordinates.
+    Locate {
+        query: String,
+        /// Choose the Nth candidate, counting from 1.
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        pick: Option<u32>,
+        /// Only keep candidates in this country, by ISO 3166 code, and choose the first one.
+        #[arg(long, value_name = "CODE")]
+        country: Option<String>,
+        /// Save the chosen place as a named location in the config file.
+        #[arg(long, value_name = "NAME")]
+        save: Option<String>,
+    },
+    /// Print the effective configuration, after merging the config file, environment
+    /// variables and flags.
+    Config,
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn should_parse_negative_coordinates() {
+        let cli = Cli::parse_from(["mausam", "-33.92,18.42", "--hours", "3"]);
+        assert_eq!(cli.place(), Some("-33.92,18.42"));
+        assert_eq!(cli.hours, Some(3));
+
+        let cli = Cli::parse_from(["mausam", "--hours", "3"]);
+        assert_eq!(cli.place(), None);
+
+        let cli = Cli::parse_from(["mausam", "forecast", "--lat", "-33.92", "--lon", "-18.42"]);
+        assert_eq!((cli.lat, cli.lon), (Some(-33.92), Some(-18.42)));
+        assert_eq!(cli.place(), None);
+    }
+
+    #[test]
+    fn should_require_both_coordinates() {
+        assert!(Cli::try_parse_from(["mausam", "--lat", "48.85"]).is_err());
+    }
 }
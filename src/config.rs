@@ -1,196 +1,722 @@
-use std::time::{Duration, SystemTime};
+//! mod `config` loads the layered configuration of `mausam`.
+//!
+//! Settings are merged from the following sources, later sources overriding earlier ones:
+//!
+//! 1. Built-in defaults.
+//! 2. The TOML config file, `$XDG_CONFIG_HOME/mausam/config.toml` (usually
+//!    `~/.config/mausam/config.toml`), or the file given by `--config` / `MAUSAM_CONFIG`.
+//! 3. Environment variables, also read from a `.env` file in the current directory.
+//! 4. CLI flags.
+//!
+//! # Example
+//!
+//! ```toml
+//! api_key = "<your API key>"
+//! place = "Paris"
+//! units = "metric"
+//! wind_unit = "kmh"
+//! provider = "openweathermap"
+//!
+//! [location]
+//! geoip_db = "/usr/share/GeoIP/GeoLite2-City.mmdb"
+//! strategies = ["cli", "home", "cache", "geoip"]
+//!
+//! [notification]
+//! enabled = true
+//! timeout = 5000
+//!
+//! [cache]
+//! location_ttl = 86400
+//! weather_ttl = 600
+//!
+//! [http]
+//! connect_timeout = 5000
+//! response_timeout = 15000
+//! retries = 3
+//!
+//! [locations.office]
+//! lat = 48.8589
+//! lon = 2.32
+//! label = "Paris office"
+//!
+//! [locations.home]
+//! place = "Lyon"
+//! ```
 
-use toml;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    net::IpAddr,
+    path::{Path, PathBuf},
+};
 
-lazy_static! {
-    static ref LAST_UPDATED: std::sync::Mutex<SystemTime> =
-        std::sync::Mutex::new(SystemTime::now());
-    static ref LOCATION: std::sync::Mutex<Option<Location>> = std::sync::Mutex::new(None);
+use anyhow::{anyhow, bail, Context};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{
+        AlertRule, DistanceUnit, PressureUnit, ProviderKind, Query, SpeedUnit, Strategy, TempUnit,
+        APP_NAME,
+    },
+    cli::Cli,
+    output::Format,
+};
+
+/// Environment variable that overrides the path of the config file.
+pub const CONFIG_VAR: &str = "MAUSAM_CONFIG";
+
+/// Environment variable that holds the `OpenWeatherMap` API key.
+pub const API_KEY_VAR: &str = "WEATHER_API_KEY";
+
+/// Environment variable that overrides the default place.
+pub const PLACE_VAR: &str = "MAUSAM_PLACE";
+
+/// Environment variable that overrides the units.
+pub const UNITS_VAR: &str = "MAUSAM_UNITS";
+
+/// Environment variable that overrides the language.
+pub const LANG_VAR: &str = "MAUSAM_LANG";
+
+/// Locale environment variables the language defaults to, in order of precedence.
+const LOCALE_VARS: [&str; 3] = ["LC_ALL", "LC_MESSAGES", "LANG"];
+
+/// Environment variable that overrides the weather provider.
+pub const PROVIDER_VAR: &str = "MAUSAM_PROVIDER";
+
+/// Effective configuration after all layers are merged.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// `OpenWeatherMap` API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// Place used when none is passed on the command line.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub place: Option<String>,
+    pub units: Units,
+    /// Wind speed unit, instead of the one of `units`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wind_unit: Option<SpeedUnit>,
+    /// Pressure unit, instead of the one of `units`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pressure_unit: Option<PressureUnit>,
+    /// Visibility unit, instead of the one of `units`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub visibility_unit: Option<DistanceUnit>,
+    /// Language of the condition descriptions and decimal separator, e.g. `de` or `pt_br`.
+    /// Defaults to the system locale.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    /// Output format, each report has its own default when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Format>,
+    /// Weather provider, picked from the API key when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderKind>,
+    pub location: LocationConfig,
+    pub notification: NotificationConfig,
+    pub changes: ChangesConfig,
+    pub cache: CacheConfig,
+    pub http: HttpConfig,
+    /// Named locations, e.g. saved by `mausam locate --save`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub locations: BTreeMap<String, SavedLocation>,
+    /// Rules of `[[alerts]]` tables. When set, the weather is only notified when one matches.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<AlertRule>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Location {
-    latitude: f64,
-    longitude: f64,
-    accuracy: f64,
+/// Unit system used to display values, named after the `OpenWeatherMap` `units` parameter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    /// Celsius, m/s, hPa and km.
+    #[default]
+    Metric,
+    /// Fahrenheit, mph, inHg and miles.
+    Imperial,
+    /// Kelvin, m/s, hPa and km.
+    Standard,
 }
 
-impl Location {
-    fn from_ipapi() -> anyhow::Result<Location, reqwest::Error> {
-        let response = Client::new().get("http://ip-api.com/json").send()?;
-        let json: IpApiResponse = response.json()?;
-        Ok(Location { latitude: json.lat, longitude: json.lon, accuracy: json.accuracy })
+impl Units {
+    /// The temperature unit of this unit system.
+    pub fn temp_unit(self) -> TempUnit {
+        match self {
+            Self::Metric => TempUnit::Celsius,
+            Self::Imperial => TempUnit::Fahrenheit,
+            Self::Standard => TempUnit::Kelvin,
+        }
     }
+}
 
-    fn from_config() -> anyhow::Result<Option<Location>, toml::de::Error> {
-        let config = std::fs::read_to_string("config.toml")?;
-        let config: toml::Value = toml::from_str(&config)?;
-        let location =
-            config.get("location").and_then(toml::Value::as_table).map(|location| Location {
-                latitude: location.get("latitude").and_then(toml::Value::as_float).unwrap_or(0.0),
-                longitude: location.get("longitude").and_then(toml::Value::as_float).unwrap_or(0.0),
-                accuracy: location.get("accuracy").and_then(toml::Value::as_float).unwrap_or(0.0),
-            });
-        Ok(location)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocationConfig {
+    /// `MaxMind` `GeoLite2` City database used to locate the machine offline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub geoip_db: Option<PathBuf>,
+    /// Public IP address to look up in `geoip_db`, when the machine is behind NAT.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<IpAddr>,
+    /// Ask ip-api.com when the `geoip_db` lookup fails.
+    pub ipapi_fallback: bool,
+    /// Location strategies to try, in order. Defaults to all of them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategies: Option<Vec<Strategy>>,
+}
+
+/// A named location, by place name or by coordinates.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SavedLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub place: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lat: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lon: Option<f64>,
+    /// Name to display instead of the one reported by the provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+impl SavedLocation {
+    /// The provider query for this location, by coordinates when both are set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither `place` nor both `lat` and `lon` are set, or if the
+    /// coordinates are out of range.
+    pub fn query(&self) -> anyhow::Result<Query> {
+        match (self.lat, self.lon, &self.place) {
+            (Some(lat), Some(lon), _) => {
+                let query = Query::coords(lat, lon)?;
+                Ok(match (query, &self.label) {
+                    (Query::Coords { lat, lon, .. }, Some(label)) => {
+                        Query::Coords { lat, lon, name: Some(label.clone()) }
+                    }
+                    (query, _) => query,
+                })
+            }
+            (None, None, Some(place)) => place.parse(),
+            _ => bail!("A location needs either `place` or both `lat` and `lon`"),
+        }
     }
+}
 
-    fn save_to_config(&self) -> anyhow::Result<(), toml::ser::Error> {
-        let config = toml::to_string(self)?;
-        std::fs::write("config.toml", config)?;
-        Ok(())
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// Show desktop notifications. Disable to only print to stdout.
+    pub enabled: bool,
+    /// How long the notification stays on screen, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u32>,
+    /// Show one notification for all locations instead of one per location.
+    pub combine: bool,
+    /// Template of the current weather summary, e.g. `{name} {temp}{unit}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_template: Option<String>,
+    /// Template of the current weather body, e.g. `{description}, wind {wind} {wind_dir}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body_template: Option<String>,
+    /// Directory of custom icons, named after the freedesktop weather icons, e.g.
+    /// `weather-storm.svg`. Icons missing from it come from the system theme.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_dir: Option<PathBuf>,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timeout: None,
+            combine: false,
+            summary_template: None,
+            body_template: None,
+            icon_dir: None,
+        }
     }
 }
 
-pub fn get_location() -> anyhow::Result<Location, Box<dyn std::error::Error>> {
-    let last_updated = LAST_UPDATED.lock().unwrap();
-    let location = LOCATION.lock().unwrap();
-    if let Some(location) = &*location {
-        if last_updated.elapsed()? < Duration::from_secs(60 * 60 * 24) {
-            return Ok(*location);
-        }
-    }
-    let location = match Location::from_config() {
-        Ok(Some(location)) => location,
-        _ => Location::from_ipapi()?,
-    };
-    *last_updated = SystemTime::now();
-    *location = Some(location);
-    location.save_to_config()?;
-    Ok(location)
-}
-//To check the accuracy of the location stored in the config file, it is recommended to verify it
-// against the location data obtained from a trusted source, such as the ip-api.com API.
-//
-//One approach to verifying the location stored in the config file would be to compare it against
-// the location data obtained from the API, and if there is a significant discrepancy, updating the
-// config file with the newly obtained location data. This verification process can be done
-// periodically, for example, every time the application is launched, or at a specific interval,
-// like once a week.
-//
-//Alternatively, if real-time location accuracy is critical for the application, it can be verified
-// with the API each time it is required to use the location data. This approach would increase the
-// number of API requests and might result in higher latency, but would ensure that the location
-// data is always up-to-date and accurate. Here's a simple program that demonstrates how to fetch
-// location information using the ipapi API, save it to a configuration file using the toml crate,
-// and then retrieve and check the location information from the config file:
-use serde::{Deserialize, Serialize};
-use toml;
-
-#[derive(Debug, Serialize, Deserialize)]
-struct Location {
-    latitude: f64,
-    longitude: f64,
-    city: String,
-    country: String,
+/// Change detection: only notify when the weather changed enough since the last notification.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChangesConfig {
+    /// Only notify about the current weather when it changed since the last notification.
+    pub enabled: bool,
+    /// Smallest temperature change worth a notification, in degrees of the display unit.
+    pub temp: f32,
+    /// Smallest pressure drop worth a notification, in hPa per hour.
+    pub pressure_drop: f32,
+    /// Notify when the condition group changes, e.g. from `Clouds` to `Rain`.
+    pub condition: bool,
+}
+
+impl Default for ChangesConfig {
+    fn default() -> Self {
+        Self { enabled: false, temp: 3.0, pressure_drop: 2.0, condition: true }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Directory for cached data. Defaults to `$XDG_CACHE_HOME/mausam`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+    /// How long a resolved location stays valid, in seconds.
+    pub location_ttl: u64,
+    /// How long a weather response is served from the cache, in seconds.
+    pub weather_ttl: u64,
+    /// Serve the last cached weather response whatever its age, without going online. Only
+    /// set with `--offline`.
+    #[serde(skip)]
+    pub offline: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Config {
-    location: Location,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// How long to wait for a connection, in milliseconds.
+    pub connect_timeout: u64,
+    /// How long to wait for the whole response, from sending the request to the end of its
+    /// body, in milliseconds. A server that stops sending halfway fails the request after this.
+    pub response_timeout: u64,
+    /// How many times to retry a request after a network error, a 5xx or a 429 status.
+    pub retries: u32,
+    /// Delay before the first retry, in milliseconds, doubled on each further retry.
+    pub backoff: u64,
+    /// Longest delay before a retry, in milliseconds. A longer `Retry-After` gives up.
+    pub max_backoff: u64,
 }
 
-const IP_API_URL: &str = "http://ip-api.com/json";
-const CONFIG_FILE: &str = "config.toml";
-
-// Fetch location information from the ip-api.com API
-async fn fetch_location() -> Result<Location, reqwest::Error> {
-    let client = reqwest::Client::new();
-    let response = client.get(IP_API_URL).send().await?;
-    let location: Location = response.json().await?;
-    Ok(location)
-}
-
-// Save location information to the config file
-fn save_config(location: &Location) -> Result<(), std::io::Error> {
-    let config = Config { location: location.clone() };
-    let config_string = toml::to_string(&config)?;
-    std::fs::write(CONFIG_FILE, config_string)?;
-    Ok(())
-}
-
-// Load location information from the config file
-fn load_config() -> Result<Location, toml::de::Error> {
-    let config_string = std::fs::read_to_string(CONFIG_FILE)?;
-    let config: Config = toml::from_str(&config_string)?;
-    Ok(config.location)
-}
-/// In this program, the location information is fetched from the ipapi API and saved to a
-/// configuration file using the toml crate. The location information is stored in a struct named
-/// Location. The program first tries to load the location information from the config file. If the
-/// config file doesn't exist or can't be parsed, the program fetches the location information again
-/// from the API and saves it to
-#[tokio::main]
-async fn try_fetcher_main() {
-    // First, try to load location information from the config file
-    let location = match load_config() {
-        Ok(location) => location,
-        Err(_) => {
-            // If the config file doesn't exist or can't be parsed, fetch the location information
-            let location = fetch_location().await.unwrap();
-            // And then save it to the config file
-            save_config(&location).unwrap();
-            location
-        }
-    };
-
-    println!("Location information: {:?}", location);
-
-    // Check if the location information is correct by fetching it again from the API
-    let current_location = fetch_location().await.unwrap();
-    if location == current_location {
-        println!("Location information is up-to-date and correct");
-    } else {
-        println!("Location information is outdated, updating...");
-        save_config(&current_location).unwrap();
-    }
-}
-
-mod verify {
-    //! This program will check the location of the user every hour and update it if necessary. The
-    //! location is stored in a Config struct, which is read from a configuration file. If the
-    //! location retrieved from ip-api.com is different from the stored location, the stored
-    //! location is updated with the new location.
-
-    use anyhow::Result;
-    use maxminddb::{geoip2::Country, Reader};
-    use reqwest::Client;
-    use serde::Deserialize;
-    use tokio::time::interval;
-
-    #[derive(Debug, Deserialize)]
-    struct Config {
-        location: String,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct IpApiResponse {
-        country: String,
-    }
-
-    async fn verify_location(config: &Config) -> Result<String> {
-        let client = Client::new();
-        let response = client.get("http://ip-api.com/json").send().await?;
-        let json: IpApiResponse = response.json().await?;
-        if config.location != json.country {
-            Ok(json.country)
-        } else {
-            Ok(config.location)
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: 5000,
+            response_timeout: 15000,
+            retries: 3,
+            backoff: 500,
+            max_backoff: 30000,
         }
-    }
-
-    #[tokio::main]
-    async fn main() -> Result<()> {
-        let config = Config { location: "US".to_owned() };
+    }
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self { dir: None, location_ttl: 60 * 60 * 24, weather_ttl: 10 * 60, offline: false }
+    }
+}
+
+impl Config {
+    /// Load the file, environment and CLI layers on top of the defaults.
+    ///
+    /// A missing config file is not an error, an unreadable or invalid one is.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the config file cannot be read or parsed, or if an environment
+    /// variable holds an invalid value.
+    pub fn load(cli: &Cli) -> anyhow::Result<Self> {
+        let mut config = match Self::path(cli) {
+            Some(path) if path.exists() => Self::from_file(&path)?,
+            _ => Self::default(),
+        };
+        config.merge_env(|key| env::var(key).ok())?;
+        config.merge_cli(cli);
+
+        Ok(config)
+    }
 
-        let interval = interval(Duration::from_secs(3600));
-        loop {
-            interval.tick().await;
-
-            let new_location = verify_location(&config).await?;
-            if new_location != config.location {
-                println!("Updating location: {} -> {}", config.location, new_location);
-                config.location = new_location;
+    /// Path of the config file, from `--config`, `MAUSAM_CONFIG` or the XDG config dir.
+    pub fn path(cli: &Cli) -> Option<PathBuf> {
+        cli.config
+            .clone()
+            .or_else(|| env::var_os(CONFIG_VAR).map(PathBuf::from))
+            .or_else(|| dirs::config_dir().map(|dir| dir.join(APP_NAME).join("config.toml")))
+    }
+
+    /// Parse the TOML config file at `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid config.
+    pub fn from_file(path: &Path) -> anyhow::Result<Self> {
+        let s = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file `{}`", path.display()))?;
+        toml::from_str(&s)
+            .with_context(|| format!("Failed to parse config file `{}`", path.display()))
+    }
+
+    /// Override settings with the environment variables returned by `var`.
+    fn merge_env(&mut self, var: impl Fn(&str) -> Option<String>) -> anyhow::Result<()> {
+        if let Some(api_key) = var(API_KEY_VAR).filter(|k| !k.is_empty()) {
+            self.api_key = Some(api_key);
+        }
+        if let Some(place) = var(PLACE_VAR) {
+            self.place = Some(place);
+        }
+        if let Some(units) = var(UNITS_VAR) {
+            self.units = Units::from_str(&units, true)
+                .map_err(|e| anyhow!(e).context(format!("Invalid `{UNITS_VAR}`")))?;
+        }
+        if let Some(lang) = var(LANG_VAR) {
+            self.lang = Some(lang);
+        } else if self.lang.is_none() {
+            self.lang =
+                LOCALE_VARS.iter().find_map(|key| var(key).and_then(|v| lang_of_locale(&v)));
+        }
+        if let Some(provider) = var(PROVIDER_VAR) {
+            self.provider = Some(
+                ProviderKind::from_str(&provider, true)
+                    .map_err(|e| anyhow!(e).context(format!("Invalid `{PROVIDER_VAR}`")))?,
+            );
+        }
+        Ok(())
+    }
+
+    /// Override settings with the flags passed on the command line.
+    ///
+    /// The place argument is left out, it is the `cli` strategy of the location resolver.
+    fn merge_cli(&mut self, cli: &Cli) {
+        if let Some(units) = cli.units {
+            self.units = units;
+        }
+        if let Some(format) = cli.format {
+            self.format = Some(format);
+        }
+        if let Some(lang) = &cli.lang {
+            self.lang = Some(lang.clone());
+        }
+        if let Some(provider) = cli.provider {
+            self.provider = Some(provider);
+        }
+        if let Some(geoip_db) = &cli.geoip_db {
+            self.location.geoip_db = Some(geoip_db.clone());
+        }
+        if cli.combine {
+            self.notification.combine = true;
+        }
+        if cli.changes {
+            self.changes.enabled = true;
+        }
+        if let Some(template) = &cli.template {
+            self.notification.summary_template = Some(template.clone());
+        }
+        if let Some(template) = &cli.body_template {
+            self.notification.body_template = Some(template.clone());
+        }
+        if let Some(max_age) = cli.max_age {
+            self.cache.weather_ttl = max_age;
+        }
+        if cli.no_cache {
+            self.cache.weather_ttl = 0;
+        }
+        if cli.offline {
+            self.cache.offline = true;
+        }
+    }
+
+    /// Add `location` as `[locations.<name>]` to the config file at `path`, replacing any
+    /// location of the same name.
+    ///
+    /// The rest of the file, comments included, is kept as is. A missing file is created.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, parsed or written.
+    pub fn save_location(path: &Path, name: &str, location: &SavedLocation) -> anyhow::Result<()> {
+        let s = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => {
+                return Err(anyhow!(err)
+                    .context(format!("Failed to read config file `{}`", path.display())))
             }
+        };
+        let mut doc: toml_edit::DocumentMut = s
+            .parse()
+            .with_context(|| format!("Failed to parse config file `{}`", path.display()))?;
+
+        let table = toml_edit::ser::to_document(location)?.as_table().clone();
+        let locations = doc
+            .entry("locations")
+            .or_insert_with(|| {
+                let mut locations = toml_edit::Table::new();
+                locations.set_implicit(true);
+                toml_edit::Item::Table(locations)
+            })
+            .as_table_mut()
+            .context("`locations` in the config file is not a table")?;
+        locations.insert(name, toml_edit::Item::Table(table));
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
         }
+        fs::write(path, doc.to_string())
+            .with_context(|| format!("Failed to write config file `{}`", path.display()))
+    }
+
+    /// Render the configuration as TOML, with the API key masked.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration cannot be serialized.
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(&self.masked())?)
+    }
+
+    /// The configuration with the API key masked, fit for printing.
+    pub fn masked(&self) -> Self {
+        Self { api_key: self.api_key.as_ref().map(|_| "***".to_string()), ..self.clone() }
+    }
+}
+
+/// The `OpenWeatherMap` language code of a POSIX locale, e.g. `de` for `de_DE.UTF-8` or
+/// `pt_br` for `pt_BR`. `None` for the `C` and `POSIX` locales.
+fn lang_of_locale(locale: &str) -> Option<String> {
+    let locale = locale.split(['.', '@']).next()?.to_lowercase();
+    let (lang, region) = locale.split_once('_').unwrap_or((&locale, ""));
+    match (lang, region) {
+        ("" | "c" | "posix", _) => None,
+        // The only languages `OpenWeatherMap` tells apart by region.
+        ("pt", "br") | ("zh", "cn" | "tw") => Some(locale.clone()),
+        _ => Some(lang.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    const CONFIG: &str = r#"
+api_key = "file-key"
+place = "Paris"
+units = "imperial"
+wind_unit = "knots"
+lang = "de"
+format = "tsv"
+provider = "open-meteo"
+
+[location]
+ip = "81.2.69.142"
+ipapi_fallback = true
+strategies = ["cli", "geoip"]
+
+[notification]
+enabled = false
+timeout = 5000
+combine = true
+summary_template = "{name}: {temp}{unit}"
+
+[changes]
+enabled = true
+temp = 2.5
+
+[cache]
+location_ttl = 3600
+weather_ttl = 60
+
+[http]
+retries = 0
+
+[locations.office]
+place = "Lyon"
+
+[[alerts]]
+name = "Frost"
+when = "temp < 0"
+"#;
+
+    #[test]
+    fn should_parse_config_file() {
+        let got: Config = toml::from_str(CONFIG).unwrap();
+        let expect = Config {
+            api_key: Some("file-key".to_string()),
+            place: Some("Paris".to_string()),
+            units: Units::Imperial,
+            wind_unit: Some(SpeedUnit::Knots),
+            pressure_unit: None,
+            visibility_unit: None,
+            lang: Some("de".to_string()),
+            format: Some(Format::Tsv),
+            provider: Some(ProviderKind::OpenMeteo),
+            location: LocationConfig {
+                geoip_db: None,
+                ip: Some("81.2.69.142".parse().unwrap()),
+                ipapi_fallback: true,
+                strategies: Some(vec![Strategy::Cli, Strategy::Geoip]),
+            },
+            notification: NotificationConfig {
+                enabled: false,
+                timeout: Some(5000),
+                combine: true,
+                summary_template: Some("{name}: {temp}{unit}".to_string()),
+                body_template: None,
+                icon_dir: None,
+            },
+            changes: ChangesConfig { enabled: true, temp: 2.5, ..ChangesConfig::default() },
+            cache: CacheConfig { dir: None, location_ttl: 3600, weather_ttl: 60, offline: false },
+            http: HttpConfig { retries: 0, ..HttpConfig::default() },
+            locations: BTreeMap::from([(
+                "office".to_string(),
+                SavedLocation { place: Some("Lyon".to_string()), ..SavedLocation::default() },
+            )]),
+            alerts: vec![AlertRule {
+                name: Some("Frost".to_string()),
+                when: "temp < 0".parse().unwrap(),
+            }],
+        };
+        assert_eq!(got, expect);
+    }
+
+    #[test]
+    fn should_default_missing_fields() {
+        let got: Config = toml::from_str("place = \"Pune\"").unwrap();
+        assert_eq!(got.units, Units::Metric);
+        assert!(got.notification.enabled);
+        assert_eq!(got.cache.location_ttl, 86400);
+    }
+
+    #[test]
+    fn should_reject_unknown_fields() {
+        assert!(toml::from_str::<Config>("plcae = \"Pune\"").is_err());
+    }
+
+    #[test]
+    fn should_override_file_with_env_and_env_with_cli() {
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        let env = HashMap::from([
+            (API_KEY_VAR, "env-key"),
+            (PLACE_VAR, "London"),
+            (UNITS_VAR, "standard"),
+        ]);
+        config.merge_env(|key| env.get(key).map(ToString::to_string)).unwrap();
+
+        assert_eq!(config.api_key.as_deref(), Some("env-key"));
+        assert_eq!(config.place.as_deref(), Some("London"));
+        assert_eq!(config.units, Units::Standard);
+        assert_eq!(config.provider, Some(ProviderKind::OpenMeteo));
+
+        let cli = Cli::parse_from([
+            "mausam",
+            "tokyo",
+            "--provider",
+            "openweathermap",
+            "--geoip-db",
+            "GeoLite2-City.mmdb",
+        ]);
+        config.merge_cli(&cli);
+
+        assert_eq!(config.place.as_deref(), Some("London"));
+        assert_eq!(config.location.geoip_db, Some(PathBuf::from("GeoLite2-City.mmdb")));
+        assert_eq!(config.provider, Some(ProviderKind::Openweathermap));
+        assert_eq!(config.units, Units::Standard);
+
+        config.merge_cli(&Cli::parse_from(["mausam", "--units", "imperial"]));
+        assert_eq!(config.units, Units::Imperial);
+
+        config.merge_cli(&Cli::parse_from([
+            "mausam",
+            "--template",
+            "{name}",
+            "--body-template",
+            "",
+        ]));
+        assert_eq!(config.notification.summary_template.as_deref(), Some("{name}"));
+        assert_eq!(config.notification.body_template.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn should_default_lang_to_locale() {
+        let env = HashMap::from([("LC_ALL", ""), ("LANG", "de_DE.UTF-8")]);
+        let mut config = Config::default();
+        config.merge_env(|key| env.get(key).map(ToString::to_string)).unwrap();
+        assert_eq!(config.lang.as_deref(), Some("de"));
+
+        let env = HashMap::from([(LANG_VAR, "hi"), ("LANG", "de_DE.UTF-8")]);
+        let mut config = Config::default();
+        config.merge_env(|key| env.get(key).map(ToString::to_string)).unwrap();
+        assert_eq!(config.lang.as_deref(), Some("hi"));
+
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        config.merge_env(|key| (key == "LANG").then(|| "fr_FR".to_string())).unwrap();
+        assert_eq!(config.lang.as_deref(), Some("de"));
+    }
+
+    #[test]
+    fn should_map_locale_to_lang() {
+        assert_eq!(lang_of_locale("en_IN.UTF-8").as_deref(), Some("en"));
+        assert_eq!(lang_of_locale("pt_BR").as_deref(), Some("pt_br"));
+        assert_eq!(lang_of_locale("zh_TW.UTF-8").as_deref(), Some("zh_tw"));
+        assert_eq!(lang_of_locale("de_DE@euro").as_deref(), Some("de"));
+        assert_eq!(lang_of_locale("C.UTF-8"), None);
+        assert_eq!(lang_of_locale("POSIX"), None);
+    }
+
+    #[test]
+    fn should_reject_invalid_env() {
+        let mut config = Config::default();
+        assert!(config.merge_env(|key| (key == UNITS_VAR).then(|| "kelvin".to_string())).is_err());
+    }
+
+    #[test]
+    fn should_save_location_keeping_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mausam").join("config.toml");
+        let home = SavedLocation {
+            lat: Some(48.8589),
+            lon: Some(2.32),
+            label: Some("Paris, Ile-de-France, FR".to_string()),
+            ..SavedLocation::default()
+        };
+
+        Config::save_location(&path, "home", &home).unwrap();
+        assert_eq!(Config::from_file(&path).unwrap().locations["home"], home);
+
+        fs::write(&path, format!("# my places\n{CONFIG}")).unwrap();
+        Config::save_location(&path, "home", &home).unwrap();
+        let got = Config::from_file(&path).unwrap();
+        assert_eq!(got.locations["home"], home);
+        assert_eq!(got.locations["office"].place.as_deref(), Some("Lyon"));
+        assert_eq!(got.place.as_deref(), Some("Paris"));
+        assert!(fs::read_to_string(&path).unwrap().starts_with("# my places\n"));
+    }
+
+    #[test]
+    fn should_query_saved_location() {
+        let office = SavedLocation {
+            place: Some("Paris".to_string()),
+            lat: Some(48.8589),
+            lon: Some(2.32),
+            label: Some("Office".to_string()),
+        };
+        let got = office.query().unwrap();
+        assert_eq!(
+            got,
+            Query::Coords { lat: 48.8589, lon: 2.32, name: Some("Office".to_string()) }
+        );
+
+        let home = SavedLocation { place: Some("Lyon".to_string()), ..SavedLocation::default() };
+        assert_eq!(home.query().unwrap(), Query::Place("Lyon".to_string()));
+
+        let half = SavedLocation { lat: Some(48.8589), ..SavedLocation::default() };
+        assert!(half.query().is_err());
+    }
+
+    #[test]
+    fn should_mask_api_key() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let got = config.to_toml().unwrap();
+        assert!(got.contains("api_key = \"***\""));
+        assert!(!got.contains("file-key"));
     }
 }
@@ -0,0 +1,185 @@
+//! mod `error` defines the failures `mausam` reports to its callers.
+//!
+//! Errors travel as `anyhow::Error`, with a [`MausamError`] somewhere in the chain when the
+//! cause is known. [`exit_code`] finds it again, so scripts and cron wrappers can branch on the
+//! process exit code:
+//!
+//! | Code | Error            |
+//! |------|------------------|
+//! | 1    | Any other error  |
+//! | 2    | Invalid usage    |
+//! | 3    | `MissingApiKey`  |
+//! | 4    | `InvalidApiKey`  |
+//! | 5    | `PlaceNotFound`  |
+//! | 6    | `RateLimited`    |
+//! | 7    | `Network`        |
+//! | 8    | `Decode`         |
+//! | 9    | `Notification`   |
+
+use std::{error::Error, fmt};
+
+/// Exit code of errors without a [`MausamError`] in their chain.
+pub const EXIT_FAILURE: i32 = 1;
+
+/// A failure with a known cause.
+#[derive(Debug)]
+pub enum MausamError {
+    /// No API key is configured for a provider that needs one.
+    MissingApiKey,
+    /// The provider rejected the API key.
+    InvalidApiKey,
+    /// The provider does not know the place.
+    PlaceNotFound(String),
+    /// The provider rejected the request because of too many requests.
+    RateLimited,
+    /// The request did not get a response.
+    Network(reqwest::Error),
+    /// The response could not be deserialized.
+    Decode(Box<dyn Error + Send + Sync>),
+    /// The desktop notification could not be shown.
+    Notification(notify_rust::error::Error),
+}
+
+impl MausamError {
+    /// Process exit code for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::MissingApiKey => 3,
+            Self::InvalidApiKey => 4,
+            Self::PlaceNotFound(_) => 5,
+            Self::RateLimited => 6,
+            Self::Network(_) => 7,
+            Self::Decode(_) => 8,
+            Self::Notification(_) => 9,
+        }
+    }
+}
+
+impl fmt::Display for MausamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingApiKey => write!(f, "No API key configured"),
+            Self::InvalidApiKey => write!(
+                f,
+                "Invalid API key, or not activated yet (can take a couple of hours after \
+                 subscribing)"
+            ),
+            Self::PlaceNotFound(place) => {
+                write!(f, "Place `{place}` not found, try `mausam locate {place:?}`")
+            }
+            Self::RateLimited => write!(f, "Too many requests, try again later"),
+            Self::Network(_) => write!(f, "Network request failed"),
+            Self::Decode(_) => write!(f, "Failed to deserialize the response body"),
+            Self::Notification(_) => write!(f, "Failed to show the notification"),
+        }
+    }
+}
+
+impl Error for MausamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Network(err) => Some(err),
+            Self::Decode(err) => Some(err.as_ref()),
+            Self::Notification(err) => Some(err),
+            Self::MissingApiKey
+            | Self::InvalidApiKey
+            | Self::PlaceNotFound(_)
+            | Self::RateLimited => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for MausamError {
+    /// Sort a reqwest error into a decode or a network failure, without the URL, which may
+    /// hold the API key.
+    fn from(err: reqwest::Error) -> Self {
+        let err = err.without_url();
+        if err.is_decode() {
+            Self::Decode(Box::new(err))
+        } else {
+            Self::Network(err)
+        }
+    }
+}
+
+/// Process exit code for `err`, from the first [`MausamError`] in its chain.
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<MausamError>())
+        .map_or(EXIT_FAILURE, MausamError::exit_code)
+}
+
+/// Whether `err` means the provider cannot serve the request for now, once the retries are
+/// spent: no response, a server error, or too many requests.
+pub fn is_unavailable(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| match cause.downcast_ref() {
+        Some(MausamError::Network(err)) => {
+            err.status().is_none_or(|status| status.is_server_error())
+        }
+        Some(MausamError::RateLimited) => true,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use anyhow::Context;
+    use pretty_assertions::assert_eq;
+    use wiremock::{matchers::path, Mock, MockServer, ResponseTemplate};
+
+    use super::*;
+
+    #[test]
+    fn should_find_exit_code_through_context() {
+        let err = Err::<(), _>(MausamError::PlaceNotFound("foo".to_string()))
+            .context("Failed to fetch weather from `openweathermap`")
+            .context("Failed to fetch weather")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 5);
+        assert_eq!(format!("{err:#}"), "Failed to fetch weather: Failed to fetch weather from `openweathermap`: Place `foo` not \
+             found, try `mausam locate \"foo\"`");
+    }
+
+    #[test]
+    fn should_default_exit_code() {
+        assert_eq!(exit_code(&anyhow::anyhow!("boom")), EXIT_FAILURE);
+        assert_eq!(exit_code(&MausamError::MissingApiKey.into()), 3);
+    }
+
+    #[tokio::test]
+    async fn should_tell_unavailable_providers() {
+        let server = MockServer::start().await;
+        Mock::given(path("/down")).respond_with(ResponseTemplate::new(503)).mount(&server).await;
+        Mock::given(path("/bad")).respond_with(ResponseTemplate::new(400)).mount(&server).await;
+        let status = |path: &'static str| {
+            let url = format!("{}{path}", server.uri());
+            async move { reqwest::get(url).await.unwrap().error_for_status().unwrap_err() }
+        };
+
+        assert!(is_unavailable(&MausamError::Network(status("/down").await).into()));
+        assert!(!is_unavailable(&MausamError::Network(status("/bad").await).into()));
+        let err = Err::<(), _>(MausamError::RateLimited).context("Failed GET").unwrap_err();
+        assert!(is_unavailable(&err));
+        assert!(!is_unavailable(&MausamError::InvalidApiKey.into()));
+    }
+
+    #[test]
+    fn should_have_distinct_exit_codes() {
+        let network = reqwest::Client::new().get("not a url").build().unwrap_err();
+        let decode = serde_json::from_str::<u8>("x").unwrap_err();
+        let codes = [
+            MausamError::MissingApiKey.exit_code(),
+            MausamError::InvalidApiKey.exit_code(),
+            MausamError::PlaceNotFound(String::new()).exit_code(),
+            MausamError::RateLimited.exit_code(),
+            MausamError::Network(network).exit_code(),
+            MausamError::Decode(Box::new(decode)).exit_code(),
+            MausamError::Notification("boom".into()).exit_code(),
+        ];
+        let unique: HashSet<i32> = codes.into_iter().collect();
+        assert_eq!(unique.len(), codes.len());
+        assert!(!unique.contains(&EXIT_FAILURE));
+    }
+}
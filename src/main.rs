@@ -7,7 +7,7 @@
 //!
 //! - Cross-platform
 //! - Supports arbitrary shell commands.
-//! - Constant feedback about the weather updates (`todo` current forecasts).
+//! - Constant feedback about the weather updates and multi-day forecasts.
 //!
 //! ## Examples
 //!
@@ -15,6 +15,8 @@
 //! Get weather information for Paris, France: `$ mausam paris`
 //! Get weather information for London, United Kingdom: `$ mausam london`
 //! Get weather information for New York, United States: `$ mausam "new york"`
+//! Get weather information for coordinates: `$ mausam 48.85,2.35` or
+//! `$ mausam --lat -33.92 --lon 18.42`
 //!
 //! Note: mausam supports various locations from around the world.
 //!
@@ -32,6 +34,65 @@
 //! WEATHER_API_KEY=<your API key>
 //! ```
 //!
+//! Instead of a `.env` file, the key can also live in the config file described
+//! below, so mausam works from any directory.
+//!
+//! ### Without an API key
+//!
+//! When `WEATHER_API_KEY` is not set, mausam falls back to the keyless
+//! [Open-Meteo](https://open-meteo.com/) provider. Pick a provider explicitly with
+//! `--provider openweathermap` or `--provider open-meteo`.
+//!
+//! ### Configuration file
+//!
+//! mausam reads an optional TOML file from `~/.config/mausam/config.toml`
+//! (`$XDG_CONFIG_HOME/mausam/config.toml`). Pass `--config <path>` or set
+//! `MAUSAM_CONFIG` to use another file.
+//!
+//! ```toml
+//! api_key = "<your API key>"
+//! place = "Paris"
+//! units = "metric" # metric, imperial or standard
+//! wind_unit = "kmh" # ms, kmh, mph or knots, instead of the one of `units`
+//! pressure_unit = "hpa" # hpa, inhg or mmhg
+//! visibility_unit = "km" # km or mi
+//! lang = "de" # condition language and decimal comma, defaults to the system locale
+//! format = "text" # json, json-compact, text, tsv or none
+//! provider = "openweathermap" # openweathermap or open-meteo
+//!
+//! [location]
+//! geoip_db = "/usr/share/GeoIP/GeoLite2-City.mmdb"
+//! ipapi_fallback = false
+//! strategies = ["cli", "home", "cache", "geoip", "ipapi"]
+//!
+//! [notification]
+//! enabled = true
+//! timeout = 5000 # milliseconds
+//! combine = false # one notification for all locations
+//! summary_template = "{name} {temp}{unit}"
+//! body_template = "{description}, wind {wind} {wind_dir}"
+//! icon_dir = "/home/me/.local/share/mausam/icons"
+//!
+//! [changes]
+//! enabled = false # only notify when the weather changed, see `--changes`
+//!
+//! [cache]
+//! location_ttl = 86400 # seconds
+//! weather_ttl = 600 # seconds, reuse a response younger than this
+//!
+//! [http]
+//! connect_timeout = 5000 # milliseconds
+//! response_timeout = 15000 # milliseconds, until the end of the response body
+//! retries = 3 # after network errors, 5xx and 429 responses
+//! backoff = 500 # milliseconds before the first retry, doubled on each one
+//! max_backoff = 30000 # milliseconds, a longer Retry-After gives up
+//! ```
+//!
+//! Settings are merged in this order, later ones winning: built-in defaults, the
+//! config file, environment variables (`WEATHER_API_KEY`, `MAUSAM_PLACE`,
+//! `MAUSAM_UNITS`, `MAUSAM_LANG`, `MAUSAM_PROVIDER`), then command line flags. Run
+//! `mausam config` to print the effective configuration.
+//!
 //! ## Installation
 //!
 //! ### Installation Prerequisites
@@ -54,7 +115,16 @@
 //!
 //! Mausam is a weather update desktop notifier made with Rust.
 //! By default, the temperature unit in the response is in Kelvin, but
-//! converted to Celsius before displayed in the notification UI.
+//! converted to Celsius before displayed in the notification UI. Pass
+//! `--units imperial` for Fahrenheit, mph, inHg and miles, or `--units standard`
+//! for Kelvin. The config file can pick the wind, pressure and visibility units
+//! one by one. The JSON output keeps the units of the providers: Kelvin, m/s, hPa
+//! and metres.
+//!
+//! Conditions are described in the language of the system locale, or the one
+//! passed with `--lang` (e.g. `de`, `hi` or `pt_br`), and decimals get a comma in
+//! the languages that write one: `Überwiegend bewölkt... 9°C / 11°C`,
+//! `Wind 4,2 m/s`. Open-Meteo descriptions are English only.
 //!
 //! ### Running Mausam
 //!
@@ -67,6 +137,239 @@
 //! mausam
 //! ```
 //!
+//! The location is looked up from your IP address and cached in
+//! `~/.cache/mausam/location.json` for 24 hours (`cache.location_ttl` in the config
+//! file). Pass `--refresh-location` to look it up again, e.g. after travelling.
+//!
+//! To keep the lookup on your machine, point mausam at a
+//! [MaxMind GeoLite2 City](https://dev.maxmind.com/geoip/geolite2-free-geolocation-data)
+//! database with `--geoip-db path/to/GeoLite2-City.mmdb`, or `location.geoip_db` in
+//! the config file. The machine's public address is used, so behind NAT set
+//! `location.ip` to your public IP. ip-api.com is only asked when the lookup fails
+//! and `location.ipapi_fallback = true`.
+//!
+//! A place or coordinates on the command line always win, then `place` from the config file or
+//! `MAUSAM_PLACE`, then the cached location, the `GeoLite2` database and finally
+//! ip-api.com. Restrict or reorder these strategies with `--locate-with`, or
+//! `location.strategies` in the config file; the one that answered is logged with
+//! `RUST_LOG=info`. A located machine is queried by its coordinates rather than its
+//! city name, which is often ambiguous:
+//!
+//! ```sh
+//! mausam --locate-with cache,geoip
+//! ```
+//!
+//! #### Forecast for the next days
+//!
+//! To print one line per day with the minimum and maximum temperature and the
+//! dominant condition, use the `forecast` command. Add `--notify` to also get one
+//! notification per day:
+//!
+//! ```sh
+//! mausam forecast paris --notify
+//! ```
+//!
+//! #### Hourly forecast
+//!
+//! To list the forecast slots for the next hours, with temperature, probability of
+//! precipitation, rain or snow volume and wind, pass `--hours`. `OpenWeatherMap`
+//! reports 3-hourly slots and Open-Meteo reports hourly ones:
+//!
+//! ```sh
+//! mausam paris --hours 12
+//! ```
+//!
+//! #### Places with the same name
+//!
+//! `mausam paris` uses whichever Paris the provider ranks first. To see the
+//! candidates with their state, country and coordinates, use the `locate` command.
+//! Choose one with `--pick N` or `--country FR`, and save it as a named location in
+//! the config file with `--save`. With `--country`, only places in that country
+//! are looked up and `--pick` counts those, e.g. Paris, Texas then Paris,
+//! Tennessee:
+//!
+//! ```sh
+//! mausam locate paris
+//! mausam locate paris --country US --save texas
+//! mausam locate paris --country US --pick 2 --save tennessee
+//! ```
+//!
+//! #### Several locations
+//!
+//! Name the places you watch in the config file, by place name or coordinates with
+//! an optional display label:
+//!
+//! ```toml
+//! [locations.office]
+//! lat = 48.8589
+//! lon = 2.32
+//! label = "Paris office"
+//!
+//! [locations.home]
+//! place = "Lyon"
+//! ```
+//!
+//! Fetch some of them with `--location office,home`, or all of them with `--all`.
+//! They are fetched concurrently and each gets its own notification; pass
+//! `--combine`, or set `notification.combine = true`, for a single notification
+//! listing all of them:
+//!
+//! ```sh
+//! mausam --all --combine
+//! ```
+//!
+//! #### Cached responses
+//!
+//! Responses are cached in `~/.cache/mausam/weather/` and reused for 10 minutes
+//! (`cache.weather_ttl` in the config file), so a status bar, a cron job and the
+//! shell polling the same place share a single request. Override the window for
+//! one run with `--max-age SECS`, or skip the cache with `--no-cache`:
+//!
+//! ```sh
+//! mausam --max-age 60
+//! mausam --no-cache
+//! ```
+//!
+//! #### Offline
+//!
+//! When the provider cannot be reached, or still answers with a server error or
+//! too many requests after the retries, mausam falls back to the last cached
+//! response for the place, however old, and says how old it is. Pass `--offline`
+//! to use the cache without trying the network at all:
+//!
+//! ```sh
+//! mausam --offline
+//! ```
+//!
+//! The notification reads `Paris 10.03°C (as of 42 min ago)`, forecast ones
+//! `Paris Thu 02 Feb (as of 42 min ago)`, and the JSON output gets a `stale` field:
+//!
+//! ```json
+//! "stale": {
+//!   "fetched_at": 1675061138,
+//!   "age": 2520,
+//!   "as_of": "42 min ago"
+//! }
+//! ```
+//!
+//! #### Output formats
+//!
+//! The current weather is printed as JSON and the other commands as text. Pick
+//! another format with `--format`, or `format` in the config file:
+//!
+//! - `json`: indented JSON.
+//! - `json-compact`: JSON on a single line.
+//! - `text`: one line per item, matching the notification, e.g. `Paris 10.03°C
+//!   Overcast clouds... 9°C / 11°C  Wind 4.2 m/s 250°, Pressure 1021 hPa`.
+//! - `tsv`: tab separated values in a stable column order, for scripts.
+//! - `none`: nothing, for cron jobs that only want the notification.
+//!
+//! TSV numbers are in the `--units` display units, with a decimal point and no unit
+//! suffix. The current weather columns are: name, country, latitude, longitude,
+//! observation time, temperature, feels like, minimum, maximum, humidity, pressure,
+//! wind speed, wind direction, cloudiness, visibility, condition id, condition,
+//! description and, for a stale observation, its age in seconds:
+//!
+//! ```sh
+//! mausam paris --format tsv | cut -f6
+//! ```
+//!
+//! #### Notification templates
+//!
+//! Change the text of the weather notification with `summary_template` and
+//! `body_template` in the `[notification]` table, or with `--template` and
+//! `--body-template`:
+//!
+//! ```sh
+//! mausam paris --template '{name}: {temp}{unit}, feels like {feels_like}{unit}' \
+//!   --body-template '{description}, wind {wind} from the {wind_dir}, sunset {sunset}'
+//! ```
+//!
+//! Placeholders, in braces, cover the whole observation: `name`, `country`, `lat`,
+//! `lon`, `condition`, `condition_id`, `description`, `icon`, `temp`,
+//! `feels_like`, `temp_min`, `temp_max`, `unit`, `humidity`, `pressure`, `wind`,
+//! `wind_speed`, `wind_deg`, `wind_dir` (compass point, e.g. `NW`), `clouds`,
+//! `visibility`, `time`, `dt`, `sunrise`, `sunset`, `timezone` and `as_of`. Times
+//! are local to the place, and missing values print as `-`, but `as_of` is empty
+//! unless the observation is stale. Write `{{` and `}}` for literal braces. An
+//! unknown placeholder is an error. The `text` output format uses the same
+//! templates.
+//!
+//! #### Notification icons
+//!
+//! The notification icon follows the condition, with the freedesktop weather icon
+//! names of your icon theme: `weather-clear` or `weather-clear-night`,
+//! `weather-few-clouds` or `weather-few-clouds-night`, `weather-overcast`,
+//! `weather-showers-scattered` for drizzle and light rain, `weather-showers`,
+//! `weather-storm`, `weather-snow`, `weather-fog` and `weather-severe-alert` for
+//! squalls and tornadoes.
+//!
+//! To use your own icons, set `icon_dir` in the `[notification]` table to a
+//! directory of `<icon name>.svg` or `.png` files, e.g. `weather-storm.svg`. Icons
+//! missing from it come from the system theme.
+//!
+//! Severe weather, e.g. a thunderstorm, heavy rain or snow, freezing rain or a
+//! tornado, is sent as a critical notification, which most notification servers
+//! keep on screen until dismissed. Each notification replaces the previous one for
+//! the same place, so repeated runs from cron update it instead of stacking new
+//! ones.
+//!
+//! #### Weather alerts
+//!
+//! To only be notified when something matters, add `[[alerts]]` rules to the
+//! config file. A rule tests the fields of the `OpenWeatherMap` response: `temp`,
+//! `feels_like`, `temp_min`, `temp_max`, `pressure`, `humidity`, `wind.speed`,
+//! `wind.deg`, `clouds`, `visibility`, `weather.id`, `weather.main` and
+//! `weather.description`:
+//!
+//! ```toml
+//! [[alerts]]
+//! name = "Frost"
+//! when = "temp < 0"
+//!
+//! [[alerts]]
+//! name = "Storm"
+//! when = "weather.id in 200..=232 or wind.speed > 15 m/s"
+//!
+//! [[alerts]]
+//! when = "humidity > 90 and weather.main == Clouds"
+//! ```
+//!
+//! Compare numbers with `<`, `<=`, `>`, `>=`, `==` and `!=`, or test a range with
+//! `in 200..=232` (end included) or `in 0..5` (end excluded), and join tests with
+//! `and` and `or`, `and` binding tighter. A number without a unit is in the
+//! `--units` display units, otherwise write one of `°C`, `°F`, `K`, `m/s`,
+//! `km/h`, `mph`, `kn`, `hPa`, `inHg`, `mmHg`, `m`, `km`, `mi` or `%`. Text
+//! fields compare with `==` and `!=`, ignoring case.
+//!
+//! With rules set, the weather notification only shows when a rule matches, as a
+//! critical notification whose summary starts with the names of the matching
+//! rules, e.g. `Frost: Paris -2.5°C`. Runs where nothing matches stay silent, which
+//! suits a cron job. The forecast notifications are not filtered.
+//!
+//! #### Change detection
+//!
+//! An hourly cron job repeating `Broken clouds` gets old fast. With `--changes`, or
+//! `enabled = true` in the `[changes]` table, the weather is only notified when it
+//! changed since the last notification for the same location:
+//!
+//! ```toml
+//! [changes]
+//! enabled = true
+//! temp = 3.0 # degrees of the display unit, up or down
+//! pressure_drop = 2.0 # hPa per hour, a sign of a coming storm
+//! condition = true # e.g. from Clouds to Rain
+//! ```
+//!
+//! The changes start the notification body, e.g. `Light rain, was clouds,
+//! Temperature down 3.5°C`. The last notified observation of each location is
+//! kept in `observations.json` in the cache directory, so slow drifts add up until
+//! they cross a threshold. The pressure trend is measured since the last run
+//! instead, so a fast drop after a quiet day still notifies. The first run for a
+//! location always notifies, and a stale observation served from the cache never
+//! does. With `[[alerts]]` rules too, a notification shows when a rule matches or
+//! the weather changed.
+//!
 //! #### Running Mausam Periodically with Cron
 //!
 //! Schedule cron jobs to run on a time interval for the current user.
@@ -78,10 +381,10 @@
 //!
 //! - Have `crontab` installed on your system
 //! - Have Mausam installed on your system, with `Cargo`, the Rust's toolchain.
-//!   - After installation, /home/<YOUR_USER_NAME>/.cargo/bin/` contains the binary
-//!   by default.
-//! - Place the `.env` file in the `path/to/mausam` directory, as this file holds the secret
-//!   `WEATHER_API_KEY`.
+//!   - After installation, `/home/<YOUR_USER_NAME>/.cargo/bin/` contains the binary
+//!     by default.
+//! - Put the `WEATHER_API_KEY` in the config file at `~/.config/mausam/config.toml`, or use
+//!   the keyless Open-Meteo provider.
 //!
 //! ##### Usage with `crontab`
 //!
@@ -97,7 +400,7 @@
 //!
 //!   ```crontab
 //!   # Run mausam (weather notification cli) every 60 minutes
-//!   */60 * * * * cd ~/path/to/mausam/ && ~/.cargo/bin/mausam
+//!   */60 * * * * ~/.cargo/bin/mausam --format none --changes
 //!   ```
 //!
 //! - Save the file and exit your editor.
@@ -113,22 +416,24 @@
 //!
 //! ### Terminal output with API response
 //!
+//! The weather provider's response is mapped into a provider-neutral observation
+//! before it is printed.
+//!
 //! ```sh
 //! $ mausam paris
 //! {
+//!   "name": "Paris",
+//!   "country": "FR",
 //!   "coord": {
 //!     "lon": 2.3488,
 //!     "lat": 48.8534
 //!   },
-//!   "weather": [
-//!     {
-//!       "id": 804,
-//!       "main": "Clouds",
-//!       "description": "overcast clouds",
-//!       "icon": "04d"
-//!     }
-//!   ],
-//!   "base": "stations",
+//!   "weather": {
+//!     "id": 804,
+//!     "main": "Clouds",
+//!     "description": "overcast clouds",
+//!     "icon": "04d"
+//!   },
 //!   "main": {
 //!     "temp": 283.18,
 //!     "feels_like": 282.12,
@@ -146,17 +451,9 @@
 //!     "all": 100
 //!   },
 //!   "dt": 1675343032,
-//!   "sys": {
-//!     "type": 2,
-//!     "id": 2041230,
-//!     "country": "FR",
-//!     "sunrise": 1675322401,
-//!     "sunset": 1675356505
-//!   },
-//!   "timezone": 3600,
-//!   "id": 2988507,
-//!   "name": "Paris",
-//!   "cod": 200
+//!   "sunrise": 1675322401,
+//!   "sunset": 1675356505,
+//!   "timezone": 3600
 //! }
 //! ```
 //!
@@ -176,12 +473,45 @@
 //!
 //! ## Troubleshooting
 //!
+//! ### Exit codes
+//!
+//! mausam exits with a distinct code per failure, so cron wrappers can tell a bad
+//! setup from a flaky network:
+//!
+//! | Code | Meaning                                 |
+//! |------|-----------------------------------------|
+//! | 1    | Any other error                         |
+//! | 2    | Invalid command line                    |
+//! | 3    | No API key configured                   |
+//! | 4    | Invalid API key                         |
+//! | 5    | Place not found                         |
+//! | 6    | Rate limited by the provider            |
+//! | 7    | Network error                           |
+//! | 8    | Unexpected response from the provider   |
+//! | 9    | The notification could not be shown     |
+//!
 //! ### API key doesn't work right after subscribing
 //!
 //! > Your API key is not activated yet. Within the next couple of hours,
 //! > it will be activated and ready to use.
 //! > `https://api.openweathermap.org/data/2.5/weather?lat=44.34&lon=10.99&appid={API key}`
 //!
+//! mausam reports a rejected key with exit code 4 and the message from
+//! `OpenWeatherMap`:
+//!
+//! ```text
+//! Error: Failed to fetch weather
+//!
+//! Caused by:
+//!     0: Failed to fetch weather from `openweathermap`
+//!     1: Failed GET request for `paris`
+//!     2: `OpenWeatherMap` says: Invalid API key. Please see https://openweathermap.org/faq#error401 for more info.
+//!     3: Invalid API key, or not activated yet (can take a couple of hours after subscribing)
+//! ```
+//!
+//! A place the provider does not know fails with exit code 5 and points to
+//! `mausam locate`, which lists the places it does know.
+//!
 //! If you still face issues or have questions, open an issue on the GitHub repository.
 //! The maintainers will be happy to help.
 //!
@@ -199,28 +529,28 @@
 //! and [LICENSE-MIT](LICENSE-MIT) files for details.
 
 mod app;
+mod cache;
 mod cli;
+mod config;
+mod error;
 mod models;
-
-use anyhow::{anyhow, Context};
+mod output;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     pretty_env_logger::init();
 
     match app::run().await {
-        Ok(v) => Ok(println!("{}", serde_json::to_string_pretty(&v)?)),
+        Ok((report, config)) => {
+            if let Some(out) = output::render(&report, &config)? {
+                println!("{out}");
+            }
+            Ok(())
+        }
         Err(e) => {
-            let context = anyhow::format_err!(
-                "Failed to run at `{}`: `{:#?}`",
-                std::env::current_dir()?
-                    .to_str()
-                    .context(anyhow!("Failed to find current_dir.\n>> Trace: {:#?}", e))
-                    .unwrap_err(),
-                e,
-            );
-            eprintln!("{:#?}", e.context(context));
-            std::process::exit(1)
+            let code = error::exit_code(&e);
+            eprintln!("Error: {e:?}");
+            std::process::exit(code)
         }
     }
 }
@@ -1,9 +1,94 @@
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
 
+/// Provider-neutral weather observation.
+///
+/// Every [`WeatherProvider`](crate::app::provider::WeatherProvider) maps its own response into
+/// this shape, so the notifier, unit conversion and JSON output do not depend on one vendor's
+/// schema. Units follow the `OpenWeatherMap` "standard" set: temperatures in Kelvin, pressure in
+/// hPa, wind speed in m/s and visibility in metres. Timestamps are Unix seconds (UTC) and
+/// `timezone` is the shift in seconds from UTC.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Observation {
+    pub name: String,
+    pub country: Option<String>,
+    pub coord: Coord,
+    pub weather: WeatherEntity,
+    pub main: Main,
+    pub visibility: Option<i32>,
+    pub wind: Wind,
+    pub clouds: Clouds,
+    pub dt: i64,
+    pub sunrise: Option<i64>,
+    pub sunset: Option<i64>,
+    pub timezone: i32,
+    /// Set when the observation is an older one served from the cache, because of `--offline`
+    /// or because the provider was unavailable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale: Option<Stale>,
+}
+
+/// When a stale observation was fetched, e.g. `as_of: "42 min ago"`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Stale {
+    /// Unix timestamp, in seconds, of when the observation was fetched.
+    pub fetched_at: i64,
+    /// Seconds elapsed since then.
+    pub age: i64,
+    /// The age for humans, e.g. `42 min ago`.
+    pub as_of: String,
+}
+
+impl Stale {
+    /// The age at `now` of an observation fetched at `fetched_at`.
+    pub fn new(fetched_at: i64, now: i64) -> Self {
+        let age = now - fetched_at;
+        Self { fetched_at, age, as_of: format_age(age) }
+    }
+}
+
+/// Round `secs` down to the largest fitting unit, e.g. `42 min ago` or `3 days ago`.
+fn format_age(secs: i64) -> String {
+    match secs {
+        ..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86399 => format!("{} h ago", secs / 3600),
+        86400..=172_799 => "1 day ago".to_string(),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+impl TryFrom<OpenWeatherData> for Observation {
+    type Error = anyhow::Error;
+
+    fn try_from(data: OpenWeatherData) -> anyhow::Result<Self> {
+        let weather = data
+            .weather
+            .and_then(|w| w.into_iter().next())
+            .context("Failed to get first weather vec item")?;
+
+        Ok(Self {
+            name: data.name,
+            country: Some(data.sys.country),
+            coord: data.coord,
+            weather,
+            main: data.main,
+            visibility: Some(data.visibility),
+            wind: data.wind,
+            clouds: data.clouds,
+            dt: i64::from(data.dt),
+            sunrise: Some(i64::from(data.sys.sunrise)),
+            sunset: Some(i64::from(data.sys.sunset)),
+            timezone: data.timezone,
+            stale: None,
+        })
+    }
+}
+
 /// JSON API response structure to expect from Open Weather API.
 /// [Reference](https://openweathermap.org/current)
 // Get types from... https://jvilk.com/MakeTypes/
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct OpenWeatherData {
     pub coord: Coord,
     pub weather: Option<Vec<WeatherEntity>>,
@@ -62,25 +147,32 @@ impl OpenWeatherData {
             cod: 200,
         }
     }
+}
 
-    pub fn with_coord(mut self, coord: Coord) -> Self {
-        self.coord = coord;
-        self
-    }
+/// JSON error body the `OpenWeatherMap` API sends along with a 4xx status, e.g.
+/// `{"cod":"404","message":"city not found"}`.
+/// [Reference](https://openweathermap.org/faq#error401)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenWeatherError {
+    pub cod: OpenWeatherCode,
+    pub message: String,
+}
 
-    pub fn with_name(mut self, name: &str) -> Self {
-        self.name = name.to_string();
-        self
-    }
+/// Status code in an `OpenWeatherMap` body, a string or a number depending on the endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum OpenWeatherCode {
+    Number(u16),
+    Text(String),
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Coord {
     pub lon: f32,
     pub lat: f32,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WeatherEntity {
     pub id: i32,
     pub main: String,
@@ -88,7 +180,7 @@ pub struct WeatherEntity {
     pub icon: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Main {
     /// `main.temp` Temperature.
     /// Unit Default: Kelvin, Metric: Celsius, Imperial: Fahrenheit.
@@ -100,18 +192,18 @@ pub struct Main {
     pub humidity: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Wind {
     pub speed: f32,
     pub deg: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Clouds {
     pub all: i32,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Sys {
     #[serde(rename = "type")]
     pub type_sys: i32,
@@ -121,6 +213,300 @@ pub struct Sys {
     pub sunset: i32,
 }
 
+/// Provider-neutral forecast: future slots for one place, earliest first.
+///
+/// Slots use the same units as `Observation`. `OpenWeatherMap` returns 3-hourly slots while
+/// Open-Meteo returns hourly ones.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Forecast {
+    pub name: String,
+    pub country: Option<String>,
+    pub coord: Coord,
+    pub timezone: i32,
+    pub slots: Vec<ForecastSlot>,
+    /// Set when the forecast is an older one served from the cache, like `Observation::stale`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale: Option<Stale>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ForecastSlot {
+    pub dt: i64,
+    pub weather: WeatherEntity,
+    pub main: Main,
+    pub wind: Wind,
+    pub clouds: Clouds,
+    /// Probability of precipitation, from `0.0` to `1.0`.
+    pub pop: Option<f32>,
+    /// Precipitation volume for the slot, in mm.
+    pub precipitation: Option<f32>,
+}
+
+/// JSON API response structure to expect from the Open Weather 5 day / 3 hour forecast API.
+/// [Reference](https://openweathermap.org/forecast5)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenWeatherForecast {
+    pub cnt: i32,
+    pub list: Vec<OpenWeatherForecastEntry>,
+    pub city: OpenWeatherForecastCity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenWeatherForecastEntry {
+    pub dt: i32,
+    pub main: Main,
+    pub weather: Vec<WeatherEntity>,
+    pub clouds: Clouds,
+    pub wind: Wind,
+    pub visibility: Option<i32>,
+    pub pop: Option<f32>,
+    pub rain: Option<Volume>,
+    pub snow: Option<Volume>,
+    pub dt_txt: String,
+}
+
+/// Precipitation volume for the last 3 hours, in mm.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Volume {
+    #[serde(rename = "3h")]
+    pub three_hours: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenWeatherForecastCity {
+    pub id: i32,
+    pub name: String,
+    pub coord: Coord,
+    pub country: String,
+    pub timezone: i32,
+    pub sunrise: i32,
+    pub sunset: i32,
+}
+
+impl TryFrom<OpenWeatherForecast> for Forecast {
+    type Error = anyhow::Error;
+
+    fn try_from(data: OpenWeatherForecast) -> anyhow::Result<Self> {
+        let slots = data
+            .list
+            .into_iter()
+            .map(|entry| {
+                let precipitation = match (&entry.rain, &entry.snow) {
+                    (None, None) => None,
+                    (rain, snow) => Some(
+                        rain.as_ref().map_or(0.0, |v| v.three_hours)
+                            + snow.as_ref().map_or(0.0, |v| v.three_hours),
+                    ),
+                };
+                Ok(ForecastSlot {
+                    dt: i64::from(entry.dt),
+                    weather: entry
+                        .weather
+                        .into_iter()
+                        .next()
+                        .with_context(|| format!("Missing weather at `{}`", entry.dt_txt))?,
+                    main: entry.main,
+                    wind: entry.wind,
+                    clouds: entry.clouds,
+                    pop: entry.pop,
+                    precipitation,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            name: data.city.name,
+            country: Some(data.city.country),
+            coord: data.city.coord,
+            timezone: data.city.timezone,
+            slots,
+            stale: None,
+        })
+    }
+}
+
+/// A geocoded place, one of the candidates for a place name.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Place {
+    pub name: String,
+    /// State or region, when the provider knows it.
+    pub state: Option<String>,
+    /// ISO 3166 country code.
+    pub country: Option<String>,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+impl Place {
+    /// Name, state and country joined for display, e.g. `Paris, Île-de-France, FR`.
+    pub fn label(&self) -> String {
+        [Some(&self.name), self.state.as_ref(), self.country.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// One entry of the JSON API response to expect from the `OpenWeatherMap` direct geocoding API.
+/// [Reference](https://openweathermap.org/api/geocoding-api)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenWeatherGeocodingEntry {
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+    pub country: String,
+    pub state: Option<String>,
+}
+
+impl From<OpenWeatherGeocodingEntry> for Place {
+    fn from(entry: OpenWeatherGeocodingEntry) -> Self {
+        Self {
+            name: entry.name,
+            state: entry.state,
+            country: Some(entry.country),
+            lat: entry.lat,
+            lon: entry.lon,
+        }
+    }
+}
+
+/// JSON API response structure to expect from the Open-Meteo forecast API when asked for the
+/// `current` and `daily` blocks with `timeformat=unixtime`.
+/// [Reference](https://open-meteo.com/en/docs)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoData {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub utc_offset_seconds: i32,
+    pub current: OpenMeteoCurrent,
+    pub daily: OpenMeteoDaily,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoCurrent {
+    pub time: i64,
+    pub temperature_2m: f32,
+    pub relative_humidity_2m: i32,
+    pub apparent_temperature: f32,
+    pub is_day: i32,
+    pub weather_code: i32,
+    pub cloud_cover: i32,
+    pub pressure_msl: f32,
+    pub wind_speed_10m: f32,
+    pub wind_direction_10m: i32,
+    pub visibility: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoDaily {
+    pub time: Vec<i64>,
+    pub temperature_2m_max: Vec<f32>,
+    pub temperature_2m_min: Vec<f32>,
+    pub sunrise: Vec<i64>,
+    pub sunset: Vec<i64>,
+}
+
+/// JSON API response structure to expect from the Open-Meteo forecast API when asked for the
+/// `hourly` block with `timeformat=unixtime`.
+/// [Reference](https://open-meteo.com/en/docs)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoForecast {
+    pub latitude: f32,
+    pub longitude: f32,
+    pub utc_offset_seconds: i32,
+    pub hourly: OpenMeteoHourly,
+}
+
+/// Column-wise hourly values; every vector has one entry per timestamp in `time`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoHourly {
+    pub time: Vec<i64>,
+    pub temperature_2m: Vec<f32>,
+    pub relative_humidity_2m: Vec<i32>,
+    pub apparent_temperature: Vec<f32>,
+    pub precipitation_probability: Vec<Option<i32>>,
+    pub precipitation: Vec<f32>,
+    pub is_day: Vec<i32>,
+    pub weather_code: Vec<i32>,
+    pub cloud_cover: Vec<i32>,
+    pub pressure_msl: Vec<f32>,
+    pub wind_speed_10m: Vec<f32>,
+    pub wind_direction_10m: Vec<i32>,
+}
+
+/// JSON API response structure to expect from the Open-Meteo geocoding API.
+/// [Reference](https://open-meteo.com/en/docs/geocoding-api)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoGeocoding {
+    pub results: Option<Vec<OpenMeteoPlace>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OpenMeteoPlace {
+    pub name: String,
+    pub latitude: f32,
+    pub longitude: f32,
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub admin1: Option<String>,
+}
+
+impl From<OpenMeteoPlace> for Place {
+    fn from(place: OpenMeteoPlace) -> Self {
+        Self {
+            name: place.name,
+            state: place.admin1,
+            country: place.country_code,
+            lat: f64::from(place.latitude),
+            lon: f64::from(place.longitude),
+        }
+    }
+}
+
+/// Map a WMO weather interpretation code, as used by Open-Meteo, to the closest
+/// `OpenWeatherMap` condition, so both providers share ids, groups and icons.
+/// [Reference](https://openweathermap.org/weather-conditions)
+pub fn wmo_weather(code: i32, is_day: bool) -> WeatherEntity {
+    let (id, main, description, icon) = match code {
+        0 => (800, "Clear", "clear sky", "01"),
+        1 => (801, "Clouds", "mainly clear", "02"),
+        2 => (802, "Clouds", "partly cloudy", "03"),
+        3 => (804, "Clouds", "overcast clouds", "04"),
+        45 => (741, "Fog", "fog", "50"),
+        48 => (741, "Fog", "depositing rime fog", "50"),
+        51 => (300, "Drizzle", "light intensity drizzle", "09"),
+        53 => (301, "Drizzle", "drizzle", "09"),
+        55 => (302, "Drizzle", "heavy intensity drizzle", "09"),
+        56 | 57 => (511, "Rain", "freezing drizzle", "13"),
+        61 => (500, "Rain", "light rain", "10"),
+        63 => (501, "Rain", "moderate rain", "10"),
+        65 => (502, "Rain", "heavy intensity rain", "10"),
+        66 | 67 => (511, "Rain", "freezing rain", "13"),
+        71 => (600, "Snow", "light snow", "13"),
+        73 => (601, "Snow", "snow", "13"),
+        75 => (602, "Snow", "heavy snow", "13"),
+        77 => (600, "Snow", "snow grains", "13"),
+        80 => (520, "Rain", "light intensity shower rain", "09"),
+        81 => (521, "Rain", "shower rain", "09"),
+        82 => (522, "Rain", "heavy intensity shower rain", "09"),
+        85 => (620, "Snow", "light shower snow", "13"),
+        86 => (622, "Snow", "heavy shower snow", "13"),
+        95 => (211, "Thunderstorm", "thunderstorm", "11"),
+        96 => (201, "Thunderstorm", "thunderstorm with slight hail", "11"),
+        99 => (202, "Thunderstorm", "thunderstorm with heavy hail", "11"),
+        _ => (0, "Unknown", "unknown conditions", "50"),
+    };
+
+    WeatherEntity {
+        id,
+        main: main.to_string(),
+        description: description.to_string(),
+        icon: format!("{icon}{}", if is_day { 'd' } else { 'n' }),
+    }
+}
+
 // ❯ CARGO_LOG=error cargo test
 #[cfg(test)]
 mod tests {
@@ -139,6 +525,20 @@ mod tests {
         assert_eq!(got, EXPECT_RESPONSE);
     }
 
+    #[test]
+    fn should_decode_error_bodies() {
+        let got: OpenWeatherError =
+            serde_json::from_str(r#"{"cod":"404","message":"city not found"}"#).unwrap();
+        assert_eq!(got.cod, OpenWeatherCode::Text("404".to_string()));
+        assert_eq!(got.message, "city not found");
+
+        let got: OpenWeatherError = serde_json::from_str(
+            r#"{"cod":401, "message": "Invalid API key. Please see https://openweathermap.org/faq#error401 for more info."}"#,
+        )
+        .unwrap();
+        assert_eq!(got.cod, OpenWeatherCode::Number(401));
+    }
+
     #[test]
     fn should_match_response_struct() {
         let got = OpenWeatherData::default();
@@ -147,4 +547,91 @@ mod tests {
             .unwrap();
         assert_eq!(got, expect);
     }
+
+    const OPEN_METEO_GEOCODING_RESPONSE: &str = r#"{"results":[{"id":2988507,"name":"Paris","latitude":48.85341,"longitude":2.3488,"elevation":42.0,"feature_code":"PPLC","country_code":"FR","admin1_id":3012874,"timezone":"Europe/Paris","population":2138551,"country_id":3017382,"country":"France","admin1":"Île-de-France"}],"generationtime_ms":0.6}"#;
+
+    #[test]
+    fn should_match_open_meteo_geocoding_struct() {
+        let got: OpenMeteoGeocoding = serde_json::from_str(OPEN_METEO_GEOCODING_RESPONSE)
+            .map_err(|e| anyhow!("Failed to convert to json from string: {e}"))
+            .unwrap();
+        let place = &got.results.unwrap()[0];
+        assert_eq!(place.name, "Paris");
+        assert_eq!(place.country_code.as_deref(), Some("FR"));
+        assert_eq!(place.admin1.as_deref(), Some("Île-de-France"));
+
+        let empty: OpenMeteoGeocoding =
+            serde_json::from_str(r#"{"generationtime_ms":0.2}"#).unwrap();
+        assert_eq!(empty.results, None);
+    }
+
+    const GEOCODING_RESPONSE: &str = r#"[{"name":"Paris","local_names":{"fr":"Paris","en":"Paris"},"lat":48.8588897,"lon":2.3200410217200766,"country":"FR","state":"Ile-de-France"},{"name":"Paris","local_names":{"en":"Paris"},"lat":33.6617962,"lon":-95.555513,"country":"US","state":"Texas"},{"name":"Paris","lat":0.3,"lon":0.2,"country":"XK"}]"#;
+
+    #[test]
+    fn should_map_geocoding_to_places() {
+        let entries: Vec<OpenWeatherGeocodingEntry> = serde_json::from_str(GEOCODING_RESPONSE)
+            .map_err(|e| anyhow!("Failed to convert to json from string: {e}"))
+            .unwrap();
+        let got: Vec<Place> = entries.into_iter().map(Place::from).collect();
+
+        assert_eq!(got.len(), 3);
+        assert_eq!(got[0].label(), "Paris, Ile-de-France, FR");
+        assert_eq!(got[1].country.as_deref(), Some("US"));
+        assert_eq!(got[2].label(), "Paris, XK");
+    }
+
+    const FORECAST_RESPONSE: &str = r#"{"cod":"200","message":0,"cnt":2,"list":[{"dt":1675339200,"main":{"temp":282.58,"feels_like":280.12,"temp_min":281.9,"temp_max":282.58,"pressure":1031,"sea_level":1031,"grnd_level":1025,"humidity":72,"temp_kf":0.68},"weather":[{"id":804,"main":"Clouds","description":"overcast clouds","icon":"04d"}],"clouds":{"all":100},"wind":{"speed":5.1,"deg":262,"gust":9.8},"visibility":10000,"pop":0,"sys":{"pod":"d"},"dt_txt":"2023-02-02 12:00:00"},{"dt":1675350000,"main":{"temp":281.2,"feels_like":279.3,"temp_min":280.4,"temp_max":281.2,"pressure":1030,"sea_level":1030,"grnd_level":1024,"humidity":80,"temp_kf":0.8},"weather":[{"id":500,"main":"Rain","description":"light rain","icon":"10d"}],"clouds":{"all":90},"wind":{"speed":4.2,"deg":250,"gust":8.1},"visibility":10000,"pop":0.42,"rain":{"3h":0.35},"sys":{"pod":"d"},"dt_txt":"2023-02-02 15:00:00"}],"city":{"id":2988507,"name":"Paris","coord":{"lat":48.8534,"lon":2.3488},"country":"FR","population":2138551,"timezone":3600,"sunrise":1675322401,"sunset":1675356505}}"#;
+
+    #[test]
+    fn should_convert_forecast() {
+        let data: OpenWeatherForecast = serde_json::from_str(FORECAST_RESPONSE)
+            .map_err(|e| anyhow!("Failed to convert to json from string: {e}"))
+            .unwrap();
+        let got = Forecast::try_from(data).unwrap();
+
+        assert_eq!(got.name, "Paris");
+        assert_eq!(got.timezone, 3600);
+        assert_eq!(got.slots.len(), 2);
+        assert_eq!(got.slots[0].precipitation, None);
+        assert_eq!(got.slots[1].weather.main, "Rain");
+        assert_eq!(got.slots[1].pop, Some(0.42));
+        assert_eq!(got.slots[1].precipitation, Some(0.35));
+    }
+
+    #[test]
+    fn should_map_wmo_codes_to_owm_conditions() {
+        assert_eq!(wmo_weather(0, false).icon, "01n");
+        assert_eq!(wmo_weather(63, true).main, "Rain");
+        assert_eq!(wmo_weather(95, true).id, 211);
+        assert_eq!(wmo_weather(42, true).main, "Unknown");
+    }
+
+    #[test]
+    fn should_convert_to_observation() {
+        let data = OpenWeatherData::default();
+        let got = Observation::try_from(data.clone()).unwrap();
+        assert_eq!(got.name, "London");
+        assert_eq!(got.country.as_deref(), Some("GB"));
+        assert_eq!(got.weather, data.weather.unwrap()[0]);
+        assert_eq!(got.main, data.main);
+        assert_eq!(got.sunrise, Some(1675064547));
+    }
+
+    #[test]
+    fn should_fail_observation_without_weather() {
+        let data = OpenWeatherData { weather: Some(vec![]), ..OpenWeatherData::default() };
+        assert!(Observation::try_from(data).is_err());
+    }
+
+    #[test]
+    fn should_format_stale_age() {
+        let got = Stale::new(1_000, 1_000 + 42 * 60 + 30);
+        assert_eq!(got, Stale { fetched_at: 1_000, age: 2550, as_of: "42 min ago".to_string() });
+
+        let ages =
+            [(5, "just now"), (7200, "2 h ago"), (86400, "1 day ago"), (3 * 86400, "3 days ago")];
+        for (age, expected) in ages {
+            assert_eq!(format_age(age), expected);
+        }
+    }
 }
@@ -0,0 +1,328 @@
+//! mod `output` renders a [`Report`] on stdout in the [`Format`] picked with `--format` or the
+//! `format` config key.
+//!
+//! The current weather defaults to pretty JSON and every other report to text. `text` matches
+//! the notifications, `tsv` prints tab separated columns in a stable order for scripts, and
+//! `none` prints nothing, for cron jobs that only want the notification.
+//!
+//! TSV values have no unit suffix and always use a decimal point. Temperatures, wind speeds,
+//! pressures, distances and precipitation are in the display units of `--units`.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app::{describe_current, DisplayUnits, Report, TempUnit, Temperature},
+    config::Config,
+    models::Observation,
+};
+
+/// How to print a report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// Indented JSON.
+    Json,
+    /// JSON on a single line.
+    JsonCompact,
+    /// Human summary, one line per item.
+    Text,
+    /// Tab separated values, one row per item.
+    Tsv,
+    /// Nothing.
+    None,
+}
+
+/// Render `report` in the configured format, or its default one. `None` when nothing is to be
+/// printed.
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be serialized.
+pub fn render(report: &Report, config: &Config) -> anyhow::Result<Option<String>> {
+    let format = config.format.unwrap_or(match report {
+        Report::Current(_) | Report::Locations(_) => Format::Json,
+        _ => Format::Text,
+    });
+    // The API key stays out of the output.
+    let masked;
+    let report = match report {
+        Report::Config { path, config } => {
+            masked = Report::Config { path: path.clone(), config: config.masked() };
+            &masked
+        }
+        report => report,
+    };
+
+    let out = match format {
+        Format::Json => serde_json::to_string_pretty(report)?,
+        Format::JsonCompact => serde_json::to_string(report)?,
+        Format::Text => text(report, config)?.join("\n"),
+        Format::Tsv => {
+            tsv(report, config)?.iter().map(|row| row.join("\t")).collect::<Vec<_>>().join("\n")
+        }
+        Format::None => return Ok(None),
+    };
+
+    Ok(Some(out))
+}
+
+/// Human lines for `report`, as shown in the notifications.
+fn text(report: &Report, config: &Config) -> anyhow::Result<Vec<String>> {
+    let units = DisplayUnits::from(config);
+    let one_line = |data: &Observation| -> anyhow::Result<String> {
+        let (summary, body) = describe_current(data, config)?;
+        Ok(format!("{summary}  {}", body.replace('\n', "  ")))
+    };
+
+    match report {
+        Report::Current(data) => Ok(vec![one_line(data)?]),
+        Report::Locations(data) => data.iter().map(one_line).collect(),
+        Report::Forecast(days) => Ok(days.iter().map(|day| day.describe(units.temp)).collect()),
+        Report::Hourly(hours) => hours.iter().map(|hour| hour.describe(&units)).collect(),
+        Report::Places(places) => Ok(places
+            .iter()
+            .enumerate()
+            .map(|(i, place)| {
+                format!("{}. {}  ({:.4}, {:.4})", i + 1, place.label(), place.lat, place.lon)
+            })
+            .collect()),
+        Report::Saved { name, path, place } => {
+            Ok(vec![format!("Saved `{}` as `{name}` in {}", place.label(), path.display())])
+        }
+        Report::Config { path, config } => {
+            let mut lines: Vec<String> =
+                path.iter().map(|path| format!("# {}", path.display())).collect();
+            lines.push(config.to_toml()?.trim_end().to_string());
+            Ok(lines)
+        }
+    }
+}
+
+/// Tab separated rows for `report`.
+fn tsv(report: &Report, config: &Config) -> anyhow::Result<Vec<Vec<String>>> {
+    let units = DisplayUnits { decimal: '.', ..DisplayUnits::from(config) };
+    let temp = |kelvin: f32| {
+        format!("{:.2}", Temperature::new(kelvin, TempUnit::Kelvin).to_unit(units.temp).value)
+    };
+
+    Ok(match report {
+        Report::Current(data) => vec![observation_row(data, &units)],
+        Report::Locations(data) => data.iter().map(|data| observation_row(data, &units)).collect(),
+        Report::Forecast(days) => days
+            .iter()
+            .map(|day| {
+                vec![
+                    day.date.to_string(),
+                    temp(day.temp_min),
+                    temp(day.temp_max),
+                    day.weather.id.to_string(),
+                    field(&day.weather.main),
+                    field(&day.weather.description),
+                ]
+            })
+            .collect(),
+        Report::Hourly(hours) => hours
+            .iter()
+            .map(|hour| {
+                vec![
+                    hour.time.format("%Y-%m-%dT%H:%M").to_string(),
+                    temp(hour.temp),
+                    optional(hour.pop.map(|pop| format!("{pop:.2}"))),
+                    optional(hour.precipitation.map(|mm| units.precipitation_value(mm))),
+                    units.speed_value(hour.wind.speed),
+                    hour.wind.deg.to_string(),
+                    hour.weather.id.to_string(),
+                    field(&hour.weather.main),
+                    field(&hour.weather.description),
+                ]
+            })
+            .collect(),
+        Report::Places(places) => places
+            .iter()
+            .map(|place| {
+                vec![
+                    field(&place.name),
+                    optional(place.state.as_deref().map(field)),
+                    optional(place.country.as_deref().map(field)),
+                    place.lat.to_string(),
+                    place.lon.to_string(),
+                ]
+            })
+            .collect(),
+        Report::Saved { name, path, place } => vec![vec![
+            field(name),
+            field(&path.display().to_string()),
+            place.lat.to_string(),
+            place.lon.to_string(),
+        ]],
+        Report::Config { config, .. } => config_rows(&config.to_toml()?),
+    })
+}
+
+/// Columns of an observation: name, country, lat, lon, time, temp, feels like, min, max,
+/// humidity, pressure, wind speed, wind direction, clouds, visibility, condition id, condition,
+/// description and the age in seconds of a stale observation.
+#[allow(clippy::cast_precision_loss)]
+fn observation_row(data: &Observation, units: &DisplayUnits) -> Vec<String> {
+    let temp = |kelvin: f32| {
+        format!("{:.2}", Temperature::new(kelvin, TempUnit::Kelvin).to_unit(units.temp).value)
+    };
+    let main = &data.main;
+
+    vec![
+        field(&data.name),
+        optional(data.country.as_deref().map(field)),
+        data.coord.lat.to_string(),
+        data.coord.lon.to_string(),
+        data.dt.to_string(),
+        temp(main.temp),
+        temp(main.feels_like),
+        temp(main.temp_min),
+        temp(main.temp_max),
+        main.humidity.to_string(),
+        units.pressure_value(main.pressure as f32),
+        units.speed_value(data.wind.speed),
+        data.wind.deg.to_string(),
+        data.clouds.all.to_string(),
+        optional(data.visibility.map(|metres| units.distance_value(metres as f32))),
+        data.weather.id.to_string(),
+        field(&data.weather.main),
+        field(&data.weather.description),
+        optional(data.stale.as_ref().map(|stale| stale.age.to_string())),
+    ]
+}
+
+/// One `key`, `value` row per setting of the TOML `config`, keys prefixed with their table,
+/// e.g. `cache.weather_ttl`.
+fn config_rows(config: &str) -> Vec<Vec<String>> {
+    let mut table = "";
+    config
+        .lines()
+        .filter_map(|line| {
+            // `[cache]`, or `[[alerts]]` for an array of tables.
+            if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+                table = name.trim_matches(['[', ']']);
+                return None;
+            }
+            let (key, value) = line.split_once(" = ")?;
+            let key = if table.is_empty() { key.to_string() } else { format!("{table}.{key}") };
+            Some(vec![field(&key), field(value)])
+        })
+        .collect()
+}
+
+/// `value` without the tabs and newlines that would break the columns.
+fn field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+/// An optional column, empty when missing.
+fn optional(value: Option<String>) -> String {
+    value.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::{
+        config::Units,
+        models::{OpenWeatherData, Stale},
+    };
+
+    fn london() -> Observation {
+        Observation::try_from(OpenWeatherData::default()).unwrap()
+    }
+
+    fn config(format: Format) -> Config {
+        Config { format: Some(format), ..Config::default() }
+    }
+
+    #[test]
+    fn should_default_to_json_for_weather_and_text_otherwise() {
+        let got = render(&Report::Current(london()), &Config::default()).unwrap().unwrap();
+        assert!(got.starts_with("{\n  \"name\": \"London\""));
+
+        let report = Report::Places(vec![]);
+        assert_eq!(render(&report, &Config::default()).unwrap().as_deref(), Some(""));
+    }
+
+    #[test]
+    fn should_render_compact_json_and_none() {
+        let got = render(&Report::Current(london()), &config(Format::JsonCompact)).unwrap();
+        assert!(got.unwrap().starts_with("{\"name\":\"London\",\"country\":\"GB\""));
+
+        assert_eq!(render(&Report::Current(london()), &config(Format::None)).unwrap(), None);
+    }
+
+    #[test]
+    fn should_render_text_like_the_notification() {
+        let got = render(&Report::Current(london()), &config(Format::Text)).unwrap().unwrap();
+        assert_eq!(
+            got,
+            "London 7.19°C  Broken clouds... 5°C / 9°C  Wind 6.2 m/s 300°, Pressure 1021 hPa, \
+             Visibility 10.0 km"
+        );
+    }
+
+    #[test]
+    fn should_render_stable_tsv_columns() {
+        let mut data = london();
+        data.stale = Some(Stale::new(1_000, 1_060));
+        let config =
+            Config { units: Units::Imperial, lang: Some("de".to_string()), ..config(Format::Tsv) };
+
+        let got = render(&Report::Current(data), &config).unwrap().unwrap();
+        let columns: Vec<&str> = got.split('\t').collect();
+        assert_eq!(
+            columns,
+            [
+                "London",
+                "GB",
+                "51.5085",
+                "-0.1257",
+                "1675061138",
+                "44.94",
+                "38.50",
+                "41.88",
+                "47.25",
+                "86",
+                "30.15",
+                "13.8",
+                "300",
+                "75",
+                "6.2",
+                "803",
+                "Clouds",
+                "broken clouds",
+                "60"
+            ]
+        );
+    }
+
+    #[test]
+    fn should_prefix_config_keys_with_their_table() {
+        let got = config_rows(
+            "units = \"metric\"\n\n[cache]\nweather_ttl = 600\n\n[[alerts]]\nwhen = \"temp < 0\"\n",
+        );
+        assert_eq!(
+            got,
+            [
+                ["units", "\"metric\""],
+                ["cache.weather_ttl", "600"],
+                ["alerts.when", "\"temp < 0\""]
+            ]
+        );
+    }
+
+    #[test]
+    fn should_mask_api_key() {
+        let config = Config { api_key: Some("secret".to_string()), ..config(Format::JsonCompact) };
+        let report = Report::Config { path: None, config: config.clone() };
+        let got = render(&report, &config).unwrap().unwrap();
+        assert!(!got.contains("secret"));
+        assert!(got.contains("\"api_key\":\"***\""));
+    }
+}